@@ -0,0 +1,171 @@
+use quote::quote;
+use syn::{
+    Attribute, Data, DeriveInput, Fields, Ident, LitStr, Result, ext::IdentExt, spanned::Spanned,
+};
+
+/// Returns the value of the `#[label(<option> = "..")]` attributes.
+fn label_option(attrs: &[Attribute], option: &str) -> Result<Option<LitStr>> {
+    let mut value: Option<LitStr> = None;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("label")) {
+        attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident(option) {
+                return Err(meta.error(format_args!(
+                    "unknown `label` option, expected `{}`",
+                    option
+                )));
+            }
+
+            if value.is_some() {
+                return Err(meta.error(format_args!("duplicate `label` option `{}`", option)));
+            }
+
+            value = Some(meta.value()?.parse()?);
+
+            Ok(())
+        })?;
+    }
+
+    Ok(value)
+}
+
+/// Convert a `CamelCase` identifier into `snake_case`, e.g. `HTTPMethod` into `http_method`.
+fn snake_case(ident: &str) -> String {
+    let chars = ident.chars().collect::<Vec<_>>();
+    let mut snake = String::with_capacity(ident.len() + 4);
+
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_lowercase = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+
+            if prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_lowercase) {
+                snake.push('_');
+            }
+        }
+
+        snake.extend(c.to_lowercase());
+    }
+
+    snake
+}
+
+/// Expands `#[derive(LabelSet)]`.
+pub fn derive_label_set(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    match &input.data {
+        Data::Struct(data) => {
+            if let Some(attr) = input
+                .attrs
+                .iter()
+                .find(|attr| attr.path().is_ident("label"))
+            {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`label` options of a struct are set on its fields",
+                ));
+            }
+
+            let Fields::Named(fields) = &data.fields else {
+                return Err(syn::Error::new(
+                    data.fields.span(),
+                    "`LabelSet` can only be derived for structs with named fields or fieldless enums",
+                ));
+            };
+
+            let mut labels: Vec<(LitStr, &Ident)> = vec![];
+
+            for field in &fields.named {
+                let member = field.ident.as_ref().expect("named field");
+
+                let key = match label_option(&field.attrs, "key")? {
+                    Some(key) => key,
+                    None => LitStr::new(&member.unraw().to_string(), member.span()),
+                };
+
+                if labels.iter().any(|(other, _)| other.value() == key.value()) {
+                    return Err(syn::Error::new_spanned(
+                        &key,
+                        format!("duplicate label key `{}`", key.value()),
+                    ));
+                }
+
+                labels.push((key, member));
+            }
+
+            // keys are sorted, so label pairs are canonical without sorting at runtime.
+            labels.sort_by_key(|(key, _)| key.value());
+
+            let keys = labels.iter().map(|(key, _)| key);
+            let members = labels.iter().map(|(_, member)| member);
+            let len = labels.len();
+
+            Ok(quote! {
+                impl #impl_generics metricrs::LabelSet for #ident #ty_generics #where_clause {
+                    const KEYS: &'static [&'static str] = &[#(#keys),*];
+
+                    fn label_values(&self) -> impl AsRef<[&str]> {
+                        let values: [&str; #len] = [
+                            #(metricrs::LabelValue::label_value(&self.#members)),*
+                        ];
+
+                        values
+                    }
+                }
+            })
+        }
+        Data::Enum(data) => {
+            let key = match label_option(&input.attrs, "key")? {
+                Some(key) => key,
+                None => LitStr::new(&snake_case(&ident.unraw().to_string()), ident.span()),
+            };
+
+            let mut arms = vec![];
+
+            for variant in &data.variants {
+                if !matches!(variant.fields, Fields::Unit) {
+                    return Err(syn::Error::new_spanned(
+                        &variant.fields,
+                        "`LabelSet` can only be derived for structs with named fields or fieldless enums",
+                    ));
+                }
+
+                let value = match label_option(&variant.attrs, "value")? {
+                    Some(value) => value,
+                    None => LitStr::new(
+                        &snake_case(&variant.ident.unraw().to_string()),
+                        variant.ident.span(),
+                    ),
+                };
+
+                let variant = &variant.ident;
+
+                arms.push(quote!(Self::#variant => #value));
+            }
+
+            Ok(quote! {
+                impl #impl_generics metricrs::LabelValue for #ident #ty_generics #where_clause {
+                    fn label_value(&self) -> &str {
+                        match self {
+                            #(#arms,)*
+                        }
+                    }
+                }
+
+                impl #impl_generics metricrs::LabelSet for #ident #ty_generics #where_clause {
+                    const KEYS: &'static [&'static str] = &[#key];
+
+                    fn label_values(&self) -> impl AsRef<[&str]> {
+                        [metricrs::LabelValue::label_value(self)]
+                    }
+                }
+            })
+        }
+        Data::Union(_) => Err(syn::Error::new_spanned(
+            ident,
+            "`LabelSet` can only be derived for structs with named fields or fieldless enums",
+        )),
+    }
+}
@@ -1,16 +1,126 @@
 use proc_macro::TokenStream;
-use quote::{ToTokens, quote};
-use syn::{Expr, ItemFn, LitStr, Result, meta, parse::Parser, parse_macro_input};
+use quote::quote;
+use syn::{
+    Attribute, DeriveInput, Expr, ExprLit, Ident, ImplItem, ImplItemFn, Item, ItemFn, ItemImpl,
+    ItemTrait, Lit, LitBool, LitInt, Meta, Result, ReturnType, TraitItem, TraitItemFn, Type,
+    Visibility, meta,
+    parse::Parser,
+    parse_macro_input, parse_quote,
+    visit_mut::{self, VisitMut},
+};
 
+mod label_set;
+mod metrics;
+
+/// Instrument kinds supported by `#[instrument(kind = ..)]` and `#[instrument(kinds(..))]`.
+#[derive(Clone, Copy, PartialEq)]
+enum InstrumentKind {
+    Counter,
+    Timer,
+    Gauge,
+}
+
+impl InstrumentKind {
+    fn parse(expr: &Expr) -> Result<Self> {
+        let kind = match expr {
+            Expr::Path(path) if path.qself.is_none() => path.path.get_ident(),
+            _ => None,
+        };
+
+        match kind.map(|ident| ident.to_string()).as_deref() {
+            Some("Counter") => Ok(Self::Counter),
+            Some("Timer") => Ok(Self::Timer),
+            Some("Gauge") => Ok(Self::Gauge),
+            _ => Err(syn::Error::new_spanned(
+                expr,
+                "unknown instrument kind, expected one of `Counter`, `Timer`, `Gauge`",
+            )),
+        }
+    }
+
+    fn ident(self) -> Ident {
+        let name = match self {
+            Self::Counter => "Counter",
+            Self::Timer => "Timer",
+            Self::Gauge => "Gauge",
+        };
+
+        Ident::new(name, proc_macro2::Span::call_site())
+    }
+
+    /// Returns the instrument type and the `Registry` method registering it.
+    fn instrument(self) -> (proc_macro2::TokenStream, Ident) {
+        let (ty, register) = match self {
+            Self::Counter => (quote!(metricrs::Counter), "counter"),
+            Self::Timer => (quote!(metricrs::Histogram), "histogam"),
+            Self::Gauge => (quote!(metricrs::Gauge), "gauge"),
+        };
+
+        (ty, Ident::new(register, proc_macro2::Span::call_site()))
+    }
+
+    /// Returns the name suffix of this kind in `kinds(..)`.
+    fn suffix(self) -> &'static str {
+        match self {
+            Self::Counter => ".calls",
+            Self::Timer => ".duration",
+            Self::Gauge => ".in_flight",
+        }
+    }
+
+    /// Returns the identifiers of the callsite static and of the instrument binding.
+    fn callsite_idents(self) -> (Ident, Ident) {
+        let (callsite, instrument) = match self {
+            Self::Counter => ("COUNTER", "__metricrs_counter"),
+            Self::Timer => ("TIMER", "__metricrs_timer"),
+            Self::Gauge => ("GAUGE", "__metricrs_gauge"),
+        };
+
+        (
+            Ident::new(callsite, proc_macro2::Span::call_site()),
+            Ident::new(instrument, proc_macro2::Span::call_site()),
+        )
+    }
+}
+
+/// Value of a label in `#[instrument(labels(..))]`.
+enum LabelValue {
+    /// A string literal, registered once.
+    Static(Expr),
+    /// An expression over the function arguments, evaluated on every call,
+    /// of any type implementing `AsRef<str>`.
+    Dynamic(Expr),
+}
+
+#[derive(Default)]
 struct InstrumentOptions {
-    kind: Option<Expr>,
-    fields: Vec<proc_macro2::TokenStream>,
+    kind: Option<InstrumentKind>,
+    kinds: Option<Vec<InstrumentKind>>,
+    name: Option<Expr>,
+    labels: Option<Vec<(Ident, LabelValue)>>,
+    outcome: Option<LitBool>,
+    cardinality: Option<LitInt>,
+    registry: Option<Expr>,
+    unit: Option<Ident>,
+    clock: Option<Expr>,
+    sample: Option<f64>,
+    skip: Option<Ident>,
+}
+
+/// Generates the `path` field of `DeriveOption`, the path of the instrumented function
+/// e.g. `my_crate::net::LoopFetch::fetch_once`.
+///
+/// A method instrumented on its own can't tell its self type, so the path is resolved from
+/// the fn item `__metricrs_path` declared in its body, once when the instrument is registered.
+fn function_path() -> proc_macro2::TokenStream {
+    quote! {
+        path: Some(metricrs::function_path(std::any::type_name_of_val(&__metricrs_path)))
+    }
 }
 
 impl InstrumentOptions {
-    pub fn parse(options: TokenStream) -> Result<Self> {
-        let mut kind = None;
-        let mut fields = vec![];
+    pub fn parse(options: proc_macro2::TokenStream) -> Result<Self> {
+        let mut this = Self::default();
 
         let parser = meta::parser(|meta| {
             macro_rules! error {
@@ -20,186 +130,934 @@ impl InstrumentOptions {
             }
 
             let Some(ident) = meta.path.get_ident() else {
-                error!("unsupported macro `instrument` option.");
+                error!(
+                    "unsupported `instrument` option, expected one of `kind`, `kinds`, `name`, `labels`, `outcome`, `cardinality`, `registry`, `unit`, `clock`, `sample`, `skip`"
+                );
             };
 
             match ident.to_string().as_str() {
                 "labels" => {
-                    let mut kv = vec![];
+                    if this.labels.is_some() {
+                        error!("duplicate `instrument` option `labels`");
+                    }
+
+                    let mut kv: Vec<(Ident, LabelValue)> = vec![];
 
                     meta.parse_nested_meta(|meta| {
                         let Some(ident) = meta.path.get_ident() else {
                             error!("expect label `name`.");
                         };
 
-                        let value: LitStr = meta.value()?.parse()?;
+                        if kv.iter().any(|(key, _)| key == ident) {
+                            error!("duplicate label `{}`", ident);
+                        }
 
-                        kv.push(quote! { (stringify!(#ident), #value) });
+                        let value = match meta.value()?.parse()? {
+                            expr @ Expr::Lit(ExprLit {
+                                lit: Lit::Str(_), ..
+                            }) => LabelValue::Static(expr),
+                            Expr::Lit(expr) => {
+                                return Err(syn::Error::new_spanned(
+                                    expr,
+                                    "label value literal must be a string",
+                                ));
+                            }
+                            expr => LabelValue::Dynamic(expr),
+                        };
+
+                        kv.push((ident.clone(), value));
 
                         Ok(())
                     })?;
 
-                    fields.push(
-                        quote! { #ident: Some(&[("rust_module_path",module_path!()), #(#kv),*]) },
-                    );
+                    this.labels = Some(kv);
 
                     Ok(())
                 }
                 "kind" => {
-                    let value: proc_macro2::TokenStream = match meta.value() {
-                        Ok(value) => {
-                            if kind.is_some() {
-                                error!("repeated 'instrument' option 'kind'");
-                            }
-                            let expr: Expr = value.parse()?;
-                            kind = Some(expr.clone());
-                            quote! { #ident: Some(#expr) }
-                        }
-                        Err(_) => {
-                            quote! { #ident: None }
-                        }
-                    };
+                    if this.kind.is_some() {
+                        error!("duplicate `instrument` option `kind`");
+                    }
 
-                    fields.push(value);
+                    if this.kinds.is_some() {
+                        error!("`kind` can't be combined with `kinds`");
+                    }
 
-                    return Ok(());
+                    let expr: Expr = meta.value()?.parse()?;
+                    this.kind = Some(InstrumentKind::parse(&expr)?);
+
+                    Ok(())
                 }
-                _ => {
-                    let value: proc_macro2::TokenStream = match meta.value() {
-                        Ok(value) => {
-                            let expr: Expr = value.parse()?;
-                            quote! { #ident: Some(#expr) }
-                        }
-                        Err(_) => {
-                            quote! { #ident: None }
+                "kinds" => {
+                    if this.kinds.is_some() {
+                        error!("duplicate `instrument` option `kinds`");
+                    }
+
+                    if this.kind.is_some() {
+                        error!("`kinds` can't be combined with `kind`");
+                    }
+
+                    let mut kinds = vec![];
+
+                    meta.parse_nested_meta(|meta| {
+                        let kind = InstrumentKind::parse(&Expr::Path(syn::ExprPath {
+                            attrs: vec![],
+                            qself: None,
+                            path: meta.path.clone(),
+                        }))?;
+
+                        if kinds.contains(&kind) {
+                            return Err(meta.error(format_args!(
+                                "duplicate instrument kind `{}`",
+                                kind.ident()
+                            )));
                         }
+
+                        kinds.push(kind);
+
+                        Ok(())
+                    })?;
+
+                    if kinds.is_empty() {
+                        error!("`kinds` requires at least one instrument kind");
+                    }
+
+                    this.kinds = Some(kinds);
+
+                    Ok(())
+                }
+                "name" => {
+                    if this.name.is_some() {
+                        error!("duplicate `instrument` option `name`");
+                    }
+
+                    this.name = Some(meta.value()?.parse()?);
+
+                    Ok(())
+                }
+                "outcome" => {
+                    if this.outcome.is_some() {
+                        error!("duplicate `instrument` option `outcome`");
+                    }
+
+                    this.outcome = Some(meta.value()?.parse()?);
+
+                    Ok(())
+                }
+                "registry" => {
+                    if this.registry.is_some() {
+                        error!("duplicate `instrument` option `registry`");
+                    }
+
+                    this.registry = Some(meta.value()?.parse()?);
+
+                    Ok(())
+                }
+                "unit" => {
+                    if this.unit.is_some() {
+                        error!("duplicate `instrument` option `unit`");
+                    }
+
+                    let unit: Ident = meta.value()?.parse()?;
+
+                    if !["Seconds", "Millis", "Micros", "Nanos"]
+                        .contains(&unit.to_string().as_str())
+                    {
+                        return Err(syn::Error::new_spanned(
+                            unit,
+                            "unknown time unit, expected one of `Seconds`, `Millis`, `Micros`, `Nanos`",
+                        ));
+                    }
+
+                    this.unit = Some(unit);
+
+                    Ok(())
+                }
+                "clock" => {
+                    if this.clock.is_some() {
+                        error!("duplicate `instrument` option `clock`");
+                    }
+
+                    this.clock = Some(meta.value()?.parse()?);
+
+                    Ok(())
+                }
+                "sample" => {
+                    if this.sample.is_some() {
+                        error!("duplicate `instrument` option `sample`");
+                    }
+
+                    let sample: Lit = meta.value()?.parse()?;
+
+                    let rate = match &sample {
+                        Lit::Float(rate) => rate.base10_parse::<f64>()?,
+                        Lit::Int(rate) => rate.base10_parse::<f64>()?,
+                        _ => f64::NAN,
                     };
 
-                    fields.push(value);
+                    if !(rate > 0.0 && rate <= 1.0) {
+                        return Err(syn::Error::new_spanned(
+                            sample,
+                            "sample rate must be a number in (0, 1]",
+                        ));
+                    }
+
+                    let period = (1.0 / rate).round();
+
+                    if (1.0 / rate - period).abs() > period * 1e-9 {
+                        return Err(syn::Error::new_spanned(
+                            sample,
+                            "sample rate must be `1 / n` for a whole number `n`, e.g. `0.5`, `0.1` or `0.01`",
+                        ));
+                    }
 
-                    return Ok(());
+                    this.sample = Some(rate);
+
+                    Ok(())
+                }
+                "skip" => {
+                    if this.skip.is_some() {
+                        error!("duplicate `instrument` option `skip`");
+                    }
+
+                    this.skip = Some(ident.clone());
+
+                    Ok(())
+                }
+                "cardinality" => {
+                    if this.cardinality.is_some() {
+                        error!("duplicate `instrument` option `cardinality`");
+                    }
+
+                    let cardinality: LitInt = meta.value()?.parse()?;
+                    cardinality.base10_parse::<usize>()?;
+                    this.cardinality = Some(cardinality);
+
+                    Ok(())
+                }
+                _ => {
+                    error!(
+                        "unknown `instrument` option `{}`, expected one of `kind`, `kinds`, `name`, `labels`, `outcome`, `cardinality`, `registry`, `unit`, `clock`, `sample`, `skip`",
+                        ident
+                    );
                 }
             }
         });
 
-        parser.parse(options)?;
+        parser.parse2(options)?;
+
+        if let Some(skip) = &this.skip
+            && (this.kind.is_some()
+                || this.kinds.is_some()
+                || this.name.is_some()
+                || this.labels.is_some()
+                || this.outcome.is_some()
+                || this.cardinality.is_some()
+                || this.registry.is_some()
+                || this.unit.is_some()
+                || this.clock.is_some()
+                || this.sample.is_some())
+        {
+            return Err(syn::Error::new_spanned(
+                skip,
+                "`skip` can't be combined with other `instrument` options",
+            ));
+        }
+
+        if !this.kinds().contains(&InstrumentKind::Timer) {
+            let timing = this
+                .unit
+                .as_ref()
+                .map(|unit| syn::Error::new_spanned(unit, "`unit` requires a `Timer` instrument"))
+                .or_else(|| {
+                    this.clock.as_ref().map(|clock| {
+                        syn::Error::new_spanned(clock, "`clock` requires a `Timer` instrument")
+                    })
+                });
+
+            if let Some(err) = timing {
+                return Err(err);
+            }
+        }
+
+        if let Some(cardinality) = &this.cardinality
+            && this.dynamic_labels().next().is_none()
+        {
+            return Err(syn::Error::new_spanned(
+                cardinality,
+                "`cardinality` requires at least one non-literal label value",
+            ));
+        }
+
+        Ok(this)
+    }
+
+    /// Returns the kinds of instruments to create, `Counter` by default.
+    fn kinds(&self) -> Vec<InstrumentKind> {
+        match &self.kinds {
+            Some(kinds) => kinds.clone(),
+            None => vec![self.kind.unwrap_or(InstrumentKind::Counter)],
+        }
+    }
+
+    /// Returns true if calls are recorded by outcome.
+    fn outcome(&self) -> bool {
+        self.outcome.as_ref().is_some_and(|outcome| outcome.value)
+    }
+
+    /// Returns the instruments to create and their name suffixes.
+    ///
+    /// The outcome of an in-flight call is unknown, so `outcome = true` on a `Gauge` counts
+    /// calls by outcome in `<name>.calls` alongside it, unless a `Counter` already does.
+    fn instruments(&self) -> Vec<(InstrumentKind, Option<&'static str>)> {
+        let mut instruments = self
+            .kinds()
+            .into_iter()
+            .map(|kind| (kind, self.kinds.as_ref().map(|_| kind.suffix())))
+            .collect::<Vec<_>>();
+
+        let kinds = self.kinds();
+
+        if self.outcome()
+            && kinds.contains(&InstrumentKind::Gauge)
+            && !kinds.contains(&InstrumentKind::Counter)
+        {
+            instruments.push((
+                InstrumentKind::Counter,
+                Some(InstrumentKind::Counter.suffix()),
+            ));
+        }
+
+        instruments
+    }
+
+    /// Returns an error if `name` is set, methods of `impl` blocks and traits are named by their own path.
+    fn require_unnamed(&self) -> Result<()> {
+        match &self.name {
+            Some(name) => Err(syn::Error::new_spanned(
+                name,
+                "`name` is not supported on `impl` blocks and traits, methods are named by their path",
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Generates the sampling decision `__metricrs_sampled` of the call.
+    /// Returns the decision and the weight of a sampled call.
+    fn sampling(&self) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+        let Some(sample) = self.sample else {
+            return (quote!(), quote!(1));
+        };
+
+        let sample = proc_macro2::Literal::f64_suffixed(sample);
+
+        let sampling = quote! {
+            const SAMPLER: metricrs::Sampler = metricrs::Sampler::new(#sample);
+
+            // one decision for all instruments, so they sample the same calls.
+            let __metricrs_sampled = SAMPLER.sample();
+        };
+
+        (sampling, quote!(SAMPLER.weight()))
+    }
+
+    /// Generates the `clock` and `unit` arguments of timing instruments, or `None` to time
+    /// with the default `Instant` in seconds.
+    fn timing(&self) -> Option<proc_macro2::TokenStream> {
+        if self.clock.is_none() && self.unit.is_none() {
+            return None;
+        }
+
+        let clock = match &self.clock {
+            Some(clock) => quote!(&#clock),
+            None => quote!(&metricrs::SystemClock),
+        };
+
+        let unit = self
+            .unit
+            .clone()
+            .unwrap_or_else(|| Ident::new("Seconds", proc_macro2::Span::call_site()));
+
+        Some(quote!(#clock, metricrs::TimeUnit::#unit))
+    }
+
+    /// Returns the label value expressions evaluated on every call.
+    fn dynamic_labels(&self) -> impl Iterator<Item = &Expr> {
+        self.labels
+            .iter()
+            .flatten()
+            .filter_map(|(_, value)| match value {
+                LabelValue::Static(_) => None,
+                LabelValue::Dynamic(expr) => Some(expr),
+            })
+    }
+
+    /// Generates the user set fields of `DeriveOption`.
+    ///
+    /// The `i`th non-literal label value is read from `__metricrs_labels[i]`.
+    fn fields(&self) -> Vec<proc_macro2::TokenStream> {
+        let mut fields = vec![];
+
+        if let Some(name) = &self.name {
+            fields.push(quote! { name: Some(#name) });
+        }
+
+        if let Some(labels) = &self.labels {
+            let mut dynamic = 0usize;
+
+            let kv = labels.iter().map(|(key, value)| match value {
+                LabelValue::Static(value) => quote! { (stringify!(#key), #value) },
+                LabelValue::Dynamic(_) => {
+                    let index = dynamic;
+                    dynamic += 1;
+                    quote! { (stringify!(#key), __metricrs_labels[#index]) }
+                }
+            });
+
+            fields.push(quote! { labels: Some(&[("rust_module_path",module_path!()), #(#kv),*]) });
+        }
+
+        fields
+    }
+
+    /// Generates the binding of the label values evaluated on every call,
+    /// which are borrowed on entry, before the body may move the arguments.
+    fn label_values(&self) -> proc_macro2::TokenStream {
+        if self.dynamic_labels().next().is_none() {
+            return quote!();
+        }
+
+        let values = self.dynamic_labels();
+
+        quote! {
+            let __metricrs_label_values = (#(&(#values),)*);
+        }
+    }
+
+    /// Generates the callsite of a `kind` instrument of type `ty` registered by `register`,
+    /// which binds the instrument to `__metricrs_<kind>: Option<_>`, and the statement that
+    /// borrows it as `Option<&#ty>`.
+    fn callsite(
+        &self,
+        kind: InstrumentKind,
+        ty: proc_macro2::TokenStream,
+        register: proc_macro2::TokenStream,
+    ) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+        let (callsite, instrument) = kind.callsite_idents();
+
+        let count = self.dynamic_labels().count();
+
+        // binds to the global registry by default.
+        let (new, resolve) = match &self.registry {
+            Some(registry) => (
+                quote!(with_registry),
+                quote! { || metricrs::global::StaticRegistry::registry(&#registry), },
+            ),
+            None => (quote!(new), quote!()),
+        };
+
+        if count == 0 {
+            let lookup = quote! {
+                #[allow(clippy::needless_update)]
+                static #callsite: metricrs::global::Callsite<#ty> = metricrs::global::Callsite::#new(#resolve |registry| {
+                    use metricrs::*;
+                    use DeriveKind::*;
+                    #register
+                });
+
+                let #instrument = #callsite.get();
+            };
+
+            return (lookup, quote!());
+        }
+
+        let values = (0..count).map(syn::Index::from);
+
+        let limit = match &self.cardinality {
+            Some(cardinality) => quote!(#cardinality),
+            None => quote!(metricrs::global::LabeledCallsite::<#ty>::DEFAULT_LIMIT),
+        };
+
+        let lookup = quote! {
+            #[allow(clippy::needless_update)]
+            static #callsite: metricrs::global::LabeledCallsite<#ty> = metricrs::global::LabeledCallsite::#new(#resolve #limit, |registry, __metricrs_labels| {
+                use metricrs::*;
+                use DeriveKind::*;
+                #register
+            });
+
+            let #instrument = #callsite.get(&[#(AsRef::<str>::as_ref(__metricrs_label_values.#values)),*]);
+        };
+
+        (lookup, quote!(let #instrument = #instrument.as_ref();))
+    }
+}
+
+/// Returns true if `attr` is an `#[instrument]` or `#[metricrs::instrument]` attribute,
+/// attributes of other crates with the same name, e.g. `#[tracing::instrument]`, are left alone.
+fn is_instrument(attr: &Attribute) -> bool {
+    let mut segments = attr.path().segments.iter().map(|segment| &segment.ident);
 
-        Ok(Self { kind, fields })
+    match (segments.next(), segments.next(), segments.next()) {
+        (Some(name), None, None) => name == "instrument",
+        (Some(krate), Some(name), None) => krate == "metricrs" && name == "instrument",
+        _ => false,
     }
 }
 
-/// Create measuring instruments for methods via attribute
+/// Returns true if a method of an `impl` block or a trait should be instrumented with the
+/// options of the block.
+///
+/// Methods with their own `#[instrument(..)]` are left to it, `#[instrument(skip)]` is removed
+/// and the method is left uninstrumented.
+fn instrument_method(attrs: &mut Vec<Attribute>) -> Result<bool> {
+    let Some(index) = attrs.iter().position(is_instrument) else {
+        return Ok(true);
+    };
+
+    let tokens = match &attrs[index].meta {
+        Meta::Path(_) => Default::default(),
+        meta => meta.require_list()?.tokens.clone(),
+    };
+
+    if InstrumentOptions::parse(tokens)?.skip.is_some() {
+        attrs.remove(index);
+    }
+
+    Ok(false)
+}
+
+/// Instruments all methods of an `impl` block.
+fn instrument_impl(
+    options: &InstrumentOptions,
+    mut item: ItemImpl,
+) -> Result<proc_macro2::TokenStream> {
+    for impl_item in &mut item.items {
+        // `default fn` (specialization) is left uninstrumented.
+        let ImplItem::Fn(
+            method @ ImplItemFn {
+                defaultness: None, ..
+            },
+        ) = impl_item
+        else {
+            continue;
+        };
+
+        // instruments can't be used in `const fn`.
+        if method.sig.constness.is_some() {
+            continue;
+        }
+
+        if !instrument_method(&mut method.attrs)? {
+            continue;
+        }
+
+        let ImplItemFn {
+            attrs,
+            vis,
+            sig,
+            block,
+            ..
+        } = method.clone();
+
+        let method = instrument_fn(
+            options,
+            ItemFn {
+                attrs,
+                vis,
+                sig,
+                block: Box::new(block),
+            },
+        );
+
+        *impl_item = ImplItem::Verbatim(method);
+    }
+
+    Ok(quote!(#item))
+}
+
+/// Instruments all provided methods of a trait.
+fn instrument_trait(
+    options: &InstrumentOptions,
+    mut item: ItemTrait,
+) -> Result<proc_macro2::TokenStream> {
+    for trait_item in &mut item.items {
+        let TraitItem::Fn(method) = trait_item else {
+            continue;
+        };
+
+        // instruments can't be used in `const fn`.
+        if method.default.is_none()
+            || method.sig.constness.is_some()
+            || !instrument_method(&mut method.attrs)?
+        {
+            continue;
+        }
+
+        let TraitItemFn {
+            attrs,
+            sig,
+            default,
+            ..
+        } = method.clone();
+
+        let method = instrument_fn(
+            options,
+            ItemFn {
+                attrs,
+                vis: Visibility::Inherited,
+                sig,
+                block: Box::new(default.expect("checked above")),
+            },
+        );
+
+        *trait_item = TraitItem::Verbatim(method);
+    }
+
+    Ok(quote!(#item))
+}
+
+/// Create measuring instruments for methods via attribute.
+///
+/// Applied to an `impl` block or a trait, all methods with a body are instrumented
+/// with the same options and named by their own path, `#[instrument(skip)]` opts a method out
+/// and `const fn` are left uninstrumented.
+///
+/// Without `name`, instruments are named by the path of the function, e.g. `my_crate::net::connect`,
+/// methods include their self type or trait, e.g. `my_crate::net::Conn::send` or
+/// `<my_crate::net::Conn as my_crate::net::Stream>::read`.
 #[proc_macro_attribute]
 pub fn instrument(options: TokenStream, item: TokenStream) -> TokenStream {
-    let InstrumentOptions { kind, fields } = match InstrumentOptions::parse(options) {
+    let options = match InstrumentOptions::parse(options.into()) {
         Ok(options) => options,
         Err(err) => return err.into_compile_error().into(),
     };
 
+    let item = parse_macro_input!(item as Item);
+
+    if options.skip.is_some() {
+        return quote!(#item).into();
+    }
+
+    let original = item.clone();
+
+    let expanded = match item {
+        Item::Fn(item) => Ok(instrument_fn(&options, item)),
+        Item::Impl(item) => options
+            .require_unnamed()
+            .and_then(|_| instrument_impl(&options, item)),
+        Item::Trait(item) => options
+            .require_unnamed()
+            .and_then(|_| instrument_trait(&options, item)),
+        item => Err(syn::Error::new_spanned(
+            item,
+            "`instrument` can only be applied to functions, `impl` blocks and traits",
+        )),
+    };
+
+    match expanded {
+        Ok(expanded) => expanded.into(),
+        // keeps the item, so the error is not followed by unresolved uses of it.
+        Err(err) => {
+            let err = err.into_compile_error();
+            quote!(#err #original).into()
+        }
+    }
+}
+
+/// Derive `metricrs::Metrics` for a struct of measuring instruments.
+///
+/// Field types must implement `metricrs::MetricField`: `Counter`, `Gauge` and `Histogram` are
+/// registered with the field name under the prefix, nested metrics structs under `prefix.field`.
+/// `#[metric(name = "..", labels(..), help = "..", unit = "..")]` configures a field.
+#[proc_macro_derive(Metrics, attributes(metric))]
+pub fn derive_metrics(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    match metrics::derive_metrics(input) {
+        Ok(expanded) => expanded.into(),
+        Err(err) => err.into_compile_error().into(),
+    }
+}
+
+/// Derive `metricrs::LabelSet` for a struct of label values or a fieldless enum.
+///
+/// Struct fields become label keys, renamed with `#[label(key = "..")]`, and their types must
+/// implement `metricrs::LabelValue`. Enum variants become the values of one label, derived enums
+/// also implement `metricrs::LabelValue`. The key is the `snake_case` enum name and values are the
+/// `snake_case` variant names, set them with `#[label(key = "..")]` and `#[label(value = "..")]`.
+#[proc_macro_derive(LabelSet, attributes(label))]
+pub fn derive_label_set(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    match label_set::derive_label_set(input) {
+        Ok(expanded) => expanded.into(),
+        Err(err) => err.into_compile_error().into(),
+    }
+}
+
+/// Replaces the `impl Trait` types in `ty` with `_`, e.g. `Result<_, E>` of
+/// `Result<impl Iterator, E>`, which can't be named by the body closure.
+fn infer_impl_trait(ty: &Type) -> Type {
+    struct Infer;
+
+    impl VisitMut for Infer {
+        fn visit_type_mut(&mut self, ty: &mut Type) {
+            match ty {
+                Type::ImplTrait(_) => *ty = parse_quote!(_),
+                ty => visit_mut::visit_type_mut(self, ty),
+            }
+        }
+    }
+
+    let mut ty = ty.clone();
+
+    Infer.visit_type_mut(&mut ty);
+
+    ty
+}
+
+/// Instruments one function.
+fn instrument_fn(options: &InstrumentOptions, item: ItemFn) -> proc_macro2::TokenStream {
+    let mut fields = options.fields();
+
+    fields.push(function_path());
+
     let ItemFn {
         attrs,
         vis,
         sig,
         block,
-    } = parse_macro_input!(item as ItemFn);
+    } = item;
 
-    let block = if sig.asyncness.is_some() {
-        quote! {
-            async #block.await
+    // options are still checked, so builds with and without `disabled` accept the same code.
+    if cfg!(feature = "disabled") {
+        let values = options.dynamic_labels().collect::<Vec<_>>();
+
+        if values.is_empty() {
+            return quote!(#(#attrs)* #vis #sig #block);
         }
-    } else {
-        quote!(#block)
-    };
 
-    let make_counter = || {
-        quote! {
+        // the closure is never called, it keeps the arguments of label values used.
+        return quote! {
             #(#attrs)*
             #vis #sig {
-                static COUNTER: std::sync::LazyLock<Option<metricrs::Counter>> = std::sync::LazyLock::new(|| {
-                    metricrs::global::get_global_registry().map(|registry| {
-                        use metricrs::*;
-                        use DeriveKind::*;
-                        registry.counter(DeriveOption {
-                            #(#fields,)*
-                            ..Default::default()
-                        }.into())
-                    })
-                });
+                let _ = || {
+                    #(let _ = &(#values);)*
+                };
 
-                if let Some(counter) = COUNTER.as_ref() {
-                    let r = #block;
-                    counter.increment(1);
-                    r
-                } else {
-                    #block
-                }
+                #block
             }
+        };
+    }
+
+    let (start_timer, start_outcome) = match options.timing() {
+        Some(timing) => (
+            quote!(start_timer_with(#timing)),
+            quote!(start_with(#timing)),
+        ),
+        None => (quote!(start_timer()), quote!(start())),
+    };
+
+    let instruments = options.instruments();
+
+    let (sampling, weight) = options.sampling();
+
+    // gauges are cheap and an in-flight gauge can't be scaled, so they are not sampled.
+    let sampled = |kind: InstrumentKind| options.sample.is_some() && kind != InstrumentKind::Gauge;
+
+    let mut callsites = vec![];
+    let mut entries = vec![];
+    let mut exits = vec![];
+
+    for (kind, suffix) in instruments {
+        let (ty, register) = kind.instrument();
+        let (_, instrument) = kind.callsite_idents();
+
+        let kind_ident = kind.ident();
+
+        let mut fields = fields.clone();
+
+        fields.push(quote! { kind: Some(#kind_ident) });
+
+        if let Some(suffix) = suffix {
+            fields.push(quote! { suffix: Some(#suffix) });
+        }
+
+        // the outcome of an in-flight call is unknown, so gauges don't split by it.
+        if options.outcome() && kind != InstrumentKind::Gauge {
+            callsites.push((
+                kind,
+                options.callsite(
+                    kind,
+                    quote!(metricrs::Outcomes<#ty>),
+                    quote! {
+                        Outcomes {
+                            ok: registry.#register(DeriveOption {
+                                #(#fields,)*
+                                outcome: Some("ok"),
+                                ..Default::default()
+                            }.into()),
+                            err: registry.#register(DeriveOption {
+                                #(#fields,)*
+                                outcome: Some("err"),
+                                ..Default::default()
+                            }.into()),
+                        }
+                    },
+                ),
+            ));
+
+            let guard = Ident::new(&format!("{}_outcome", instrument), instrument.span());
+
+            entries.push(quote! {
+                // records into `err` on drop, also on panic or future cancellation.
+                let #guard = #instrument.map(|outcomes| outcomes.#start_outcome.weighted(#weight));
+            });
+
+            exits.push(quote! {
+                if let Some(outcome) = #guard {
+                    outcome.finish(&__metricrs_output);
+                }
+            });
+
+            continue;
         }
+
+        callsites.push((
+            kind,
+            options.callsite(
+                kind,
+                ty,
+                quote! {
+                    registry.#register(DeriveOption {
+                        #(#fields,)*
+                        ..Default::default()
+                    }.into())
+                },
+            ),
+        ));
+
+        entries.push(match kind {
+            InstrumentKind::Counter => quote! {
+                if let Some(counter) = #instrument {
+                    counter.increment(#weight);
+                }
+            },
+            InstrumentKind::Timer => quote! {
+                // records on drop, also on panic, early return or future cancellation.
+                let _timer = #instrument.map(|timer| timer.#start_timer);
+            },
+            InstrumentKind::Gauge => quote! {
+                // decrements on drop, also on panic, early return or future cancellation.
+                let _gauge = #instrument.map(|gauge| gauge.increment_guard(1.0));
+            },
+        });
+    }
+
+    let label_values = options.label_values();
+
+    let (lookups, borrows): (Vec<_>, Vec<_>) = callsites
+        .iter()
+        .filter(|(kind, _)| !sampled(*kind))
+        .map(|(_, callsite)| callsite.clone())
+        .unzip();
+
+    let (sampled_lookups, sampled_borrows): (Vec<_>, Vec<_>) = callsites
+        .iter()
+        .filter(|(kind, _)| sampled(*kind))
+        .map(|(_, callsite)| callsite.clone())
+        .unzip();
+
+    // label values are only evaluated for calls not sampled if an unsampled instrument needs them.
+    let (label_values, sampled_label_values) = if lookups.is_empty() {
+        (quote!(), label_values)
+    } else {
+        (label_values, quote!())
     };
 
-    let make_timer = || {
+    let sampled_bindings = callsites
+        .iter()
+        .filter(|(kind, _)| sampled(*kind))
+        .map(|(kind, _)| kind.callsite_idents().1)
+        .collect::<Vec<_>>();
+
+    let nones = sampled_bindings.iter().map(|_| quote!(None));
+
+    // instruments of calls not sampled are not looked up.
+    let sampled_callsites = if sampled_bindings.is_empty() {
+        quote!()
+    } else {
         quote! {
-            #(#attrs)*
-            #vis #sig {
+            let (#(#sampled_bindings,)*) = if __metricrs_sampled {
+                #sampled_label_values
 
-                static TIMER: std::sync::LazyLock<Option<metricrs::Histogram>> = std::sync::LazyLock::new(|| {
-                    metricrs::global::get_global_registry().map(|registry| {
-                        use metricrs::*;
-                        use DeriveKind::*;
-                        registry.histogam(DeriveOption {
-                              #(#fields,)*
-                            ..Default::default()
-                        }.into())
-                    })
-                });
+                #(#sampled_lookups)*
 
-                if let Some(timer) = TIMER.as_ref() {
-                    let now = std::time::Instant::now();
-                    let r = #block;
-                    timer.record(now.elapsed().as_secs_f64());
-                    r
-                } else {
-                    #block
-                }
-            }
+                (#(#sampled_bindings,)*)
+            } else {
+                (#(#nones,)*)
+            };
+
+            #(#sampled_borrows)*
         }
     };
 
-    let make_gauge = || {
+    let body = if exits.is_empty() {
+        if sig.asyncness.is_some() {
+            quote!(async #block.await)
+        } else {
+            quote!(#block)
+        }
+    } else if sig.asyncness.is_some() {
+        // `return` in the body only returns from the async block.
         quote! {
-            #(#attrs)*
-            #vis #sig {
+            let __metricrs_output = async #block.await;
 
-                static GAUGE: std::sync::LazyLock<Option<metricrs::Gauge>> = std::sync::LazyLock::new(|| {
-                    metricrs::global::get_global_registry().map(|registry| {
-                        use metricrs::*;
-                        use DeriveKind::*;
-                        registry.gauge(DeriveOption {
-                              #(#fields,)*
-                            ..Default::default()
-                        }.into())
-                    })
-                });
+            #(#exits)*
 
-                if let Some(gauge) = GAUGE.as_ref() {
-                    gauge.increment(1f64);
-                    let r = #block;
-                    gauge.decrement(1f64);
-                    r
-                } else {
-                    #block
-                }
-            }
+            __metricrs_output
+        }
+    } else {
+        let output = match &sig.output {
+            ReturnType::Type(_, ty) => infer_impl_trait(ty),
+            ReturnType::Default => parse_quote!(()),
+        };
+
+        // `return` in the body returns from the closure, so every exit, also a `return`
+        // expanded from a macro, passes the result to `exits`.
+        quote! {
+            let __metricrs_output = metricrs::call_once(|| -> #output #block);
+
+            #[allow(unreachable_code)]
+            let __metricrs_output = {
+                #(#exits)*
+
+                __metricrs_output
+            };
+
+            __metricrs_output
         }
     };
 
-    if let Some(kind) = kind {
-        match kind.to_token_stream().to_string().as_str() {
-            "Timer" => return make_timer().into(),
-            "Gauge" => return make_gauge().into(),
-            _ => return make_counter().into(),
+    quote! {
+        #(#attrs)*
+        #vis #sig {
+            fn __metricrs_path() {}
+
+            #sampling
+
+            #label_values
+
+            #(#lookups)*
+
+            #(#borrows)*
+
+            #sampled_callsites
+
+            #(#entries)*
+
+            #body
         }
     }
-
-    return make_counter().into();
 }
@@ -0,0 +1,203 @@
+use quote::{quote, quote_spanned};
+use syn::{
+    Data, DeriveInput, Expr, ExprLit, Field, Fields, Ident, Lit, LitStr, Result, spanned::Spanned,
+};
+
+/// Options of a field set by `#[metric(..)]`.
+#[derive(Default)]
+struct MetricOptions {
+    name: Option<LitStr>,
+    labels: Option<Vec<(Ident, LitStr)>>,
+    help: Option<LitStr>,
+    unit: Option<LitStr>,
+}
+
+impl MetricOptions {
+    fn parse(field: &Field) -> Result<Self> {
+        let mut this = Self::default();
+
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("metric"))
+        {
+            attr.parse_nested_meta(|meta| {
+                macro_rules! error {
+                    ($($t:tt)+) => {
+                        return Err(meta.error(format_args!($($t)+)))
+                    };
+                }
+
+                let Some(ident) = meta.path.get_ident() else {
+                    error!("unsupported `metric` option, expected one of `name`, `labels`, `help`, `unit`");
+                };
+
+                let option = match ident.to_string().as_str() {
+                    "name" => &mut this.name,
+                    "help" => &mut this.help,
+                    "unit" => &mut this.unit,
+                    "labels" => {
+                        if this.labels.is_some() {
+                            error!("duplicate `metric` option `labels`");
+                        }
+
+                        let mut kv: Vec<(Ident, LitStr)> = vec![];
+
+                        meta.parse_nested_meta(|meta| {
+                            let Some(ident) = meta.path.get_ident() else {
+                                error!("expect label `name`.");
+                            };
+
+                            if kv.iter().any(|(key, _)| key == ident) {
+                                error!("duplicate label `{}`", ident);
+                            }
+
+                            let value = match meta.value()?.parse()? {
+                                Expr::Lit(ExprLit {
+                                    lit: Lit::Str(value),
+                                    ..
+                                }) => value,
+                                expr => {
+                                    return Err(syn::Error::new_spanned(
+                                        expr,
+                                        "label value must be a string literal",
+                                    ));
+                                }
+                            };
+
+                            kv.push((ident.clone(), value));
+
+                            Ok(())
+                        })?;
+
+                        this.labels = Some(kv);
+
+                        return Ok(());
+                    }
+                    _ => {
+                        error!(
+                            "unknown `metric` option `{}`, expected one of `name`, `labels`, `help`, `unit`",
+                            ident
+                        );
+                    }
+                };
+
+                if option.is_some() {
+                    error!("duplicate `metric` option `{}`", ident);
+                }
+
+                *option = Some(meta.value()?.parse()?);
+
+                Ok(())
+            })?;
+        }
+
+        Ok(this)
+    }
+}
+
+/// Expands `#[derive(Metrics)]`.
+pub fn derive_metrics(input: DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "`Metrics` can only be derived for structs with named fields",
+        ));
+    };
+
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new(
+            data.fields.span(),
+            "`Metrics` can only be derived for structs with named fields",
+        ));
+    };
+
+    let mut checks = vec![];
+    let mut registers = vec![];
+    let mut describes = vec![];
+
+    for field in &fields.named {
+        let ident = field.ident.as_ref().expect("named field");
+        let ty = &field.ty;
+        let options = MetricOptions::parse(field)?;
+
+        let name = match &options.name {
+            Some(name) => quote!(#name),
+            None => quote!(stringify!(#ident)),
+        };
+
+        // whether a field is nested is only known once the compiler resolves its type.
+        if options.labels.is_some() || options.help.is_some() || options.unit.is_some() {
+            checks.push(quote_spanned! {ty.span()=>
+                assert!(
+                    !<#ty as metricrs::MetricField>::NESTED,
+                    "nested metrics only support the `metric` option `name`"
+                )
+            });
+        }
+
+        let labels = options
+            .labels
+            .iter()
+            .flatten()
+            .map(|(key, value)| quote! { (stringify!(#key), #value) });
+
+        let help = match &options.help {
+            Some(help) => quote!(Some(#help)),
+            None => quote!(None),
+        };
+
+        let unit = match &options.unit {
+            Some(unit) => quote!(Some(#unit)),
+            None => quote!(None),
+        };
+
+        registers.push(quote! {
+            #ident: <#ty as metricrs::MetricField>::register_field(
+                registry,
+                &metricrs::metric_name(prefix, #name),
+                &[#(#labels),*],
+            )
+        });
+
+        describes.push(quote! {
+            descriptions.extend(<#ty as metricrs::MetricField>::describe_field(
+                &metricrs::metric_name(prefix, #name),
+                #help,
+                #unit,
+            ));
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // free constants are evaluated by `cargo check`, but cannot name generic parameters.
+    let (checks, inline_checks) = if input.generics.params.is_empty() {
+        (checks, vec![])
+    } else {
+        (vec![], checks)
+    };
+
+    Ok(quote! {
+        #(const _: () = #checks;)*
+
+        impl #impl_generics metricrs::Metrics for #ident #ty_generics #where_clause {
+            fn register(registry: &dyn metricrs::Registry, prefix: &str) -> Self {
+                #(const { #inline_checks };)*
+
+                Self {
+                    #(#registers,)*
+                }
+            }
+
+            fn describe(prefix: &str) -> Vec<metricrs::MetricDescription> {
+                let mut descriptions = vec![];
+
+                #(#describes)*
+
+                descriptions
+            }
+        }
+    })
+}
@@ -0,0 +1,94 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+/// Atomically update the `f64` stored as bits in `value`.
+#[inline]
+pub(crate) fn fetch_update_f64(value: &AtomicU64, f: impl Fn(f64) -> Option<f64>) {
+    _ = value.fetch_update(Ordering::AcqRel, Ordering::Relaxed, |curr| {
+        f(f64::from_bits(curr)).map(f64::to_bits)
+    });
+}
+
+/// Lock-free bucketed histogram storage, registries can hand it out
+/// via [`Histogram::Atomic`](crate::Histogram::Atomic).
+#[derive(Debug)]
+pub struct AtomicHistogram {
+    bounds: Arc<[f64]>,
+    buckets: Box<[AtomicU64]>,
+    count: AtomicU64,
+    sum: AtomicU64,
+    min: AtomicU64,
+    max: AtomicU64,
+}
+
+impl AtomicHistogram {
+    /// Create a histogram with bucket upper (inclusive) `bounds`,
+    /// the trailing `+Inf` bucket is implicit.
+    ///
+    /// `bounds` should be sorted in increasing order.
+    pub fn new<B: Into<Arc<[f64]>>>(bounds: B) -> Self {
+        let bounds = bounds.into();
+
+        Self {
+            buckets: (0..bounds.len() + 1).map(|_| AtomicU64::new(0)).collect(),
+            bounds,
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0f64.to_bits()),
+            min: AtomicU64::new(f64::INFINITY.to_bits()),
+            max: AtomicU64::new(f64::NEG_INFINITY.to_bits()),
+        }
+    }
+
+    /// Records a value into the histogram, `NaN` is ignored.
+    #[inline]
+    pub fn record(&self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+
+        let index = self.bounds.partition_point(|bound| *bound < value);
+
+        self.buckets[index].fetch_add(1, Ordering::Relaxed);
+
+        fetch_update_f64(&self.sum, |sum| Some(sum + value));
+        fetch_update_f64(&self.min, |min| (value < min).then_some(value));
+        fetch_update_f64(&self.max, |max| (value > max).then_some(value));
+
+        self.count.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Bucket upper bounds.
+    pub fn bounds(&self) -> &[f64] {
+        &self.bounds
+    }
+
+    /// Returns an iterator over the non-cumulative sample counts per bucket,
+    /// yields `bounds().len() + 1` items.
+    pub fn buckets(&self) -> impl Iterator<Item = u64> + '_ {
+        self.buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+    }
+
+    /// Total number of recorded samples.
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Acquire)
+    }
+
+    /// Sum of all recorded samples.
+    pub fn sum(&self) -> f64 {
+        f64::from_bits(self.sum.load(Ordering::Relaxed))
+    }
+
+    /// Minimum recorded sample, `None` if nothing is recorded.
+    pub fn min(&self) -> Option<f64> {
+        (self.count() > 0).then(|| f64::from_bits(self.min.load(Ordering::Relaxed)))
+    }
+
+    /// Maximum recorded sample, `None` if nothing is recorded.
+    pub fn max(&self) -> Option<f64> {
+        (self.count() > 0).then(|| f64::from_bits(self.max.load(Ordering::Relaxed)))
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    io,
+    sync::{
+        Arc, LazyLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Source of monotonic time for timing instruments, see [`Histogram::start_timer_with`](crate::Histogram::start_timer_with).
+pub trait Clock: Send + Sync {
+    /// Returns the current monotonic time in nanoseconds, from an unspecified origin.
+    fn now(&self) -> u64;
+}
+
+/// Unit of the durations recorded by timing instruments.
+///
+/// Durations in milliseconds, microseconds and nanoseconds are recorded as whole numbers.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimeUnit {
+    /// Seconds, with fractional part.
+    #[default]
+    Seconds,
+    /// Whole milliseconds.
+    Millis,
+    /// Whole microseconds.
+    Micros,
+    /// Whole nanoseconds.
+    Nanos,
+}
+
+impl TimeUnit {
+    /// Convert a duration in `nanos` into this unit.
+    #[inline]
+    pub fn convert(self, nanos: u64) -> f64 {
+        match self {
+            TimeUnit::Seconds => nanos as f64 / 1e9,
+            TimeUnit::Millis => (nanos / 1_000_000) as f64,
+            TimeUnit::Micros => (nanos / 1_000) as f64,
+            TimeUnit::Nanos => nanos as f64,
+        }
+    }
+}
+
+/// Start of a timed call, the default [`Instant`] is read without dynamic dispatch.
+#[derive(Clone, Copy)]
+pub(crate) enum Stopwatch<'a> {
+    /// Started at an [`Instant`], elapsed time is in seconds.
+    Instant(Instant),
+    /// Started at `start` of `clock`, elapsed time is in `unit`.
+    Clock {
+        clock: &'a dyn Clock,
+        unit: TimeUnit,
+        start: u64,
+    },
+}
+
+impl<'a> Stopwatch<'a> {
+    /// Start at [`Instant::now`].
+    #[inline]
+    pub(crate) fn start() -> Self {
+        Self::Instant(Instant::now())
+    }
+
+    /// Start at the current time of `clock`.
+    #[inline]
+    pub(crate) fn start_with(clock: &'a dyn Clock, unit: TimeUnit) -> Self {
+        Self::Clock {
+            clock,
+            unit,
+            start: clock.now(),
+        }
+    }
+
+    /// Returns the elapsed time since the start.
+    #[inline]
+    pub(crate) fn elapsed(&self) -> f64 {
+        match self {
+            Self::Instant(start) => start.elapsed().as_secs_f64(),
+            Self::Clock { clock, unit, start } => unit.convert(clock.now().saturating_sub(*start)),
+        }
+    }
+}
+
+/// A clock reads [`Instant::now`], like [`Histogram::start_timer`](crate::Histogram::start_timer) does.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> u64 {
+        static ORIGIN: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+        ORIGIN.elapsed().as_nanos() as u64
+    }
+}
+
+/// A cheap clock with a bounded `resolution`, reading it costs a single atomic load.
+///
+/// The time is updated by a background thread every `resolution`, which exits
+/// once the clock is dropped.
+#[derive(Debug)]
+pub struct CoarseClock {
+    now: Arc<AtomicU64>,
+}
+
+impl CoarseClock {
+    /// Create a new clock updated every `resolution`.
+    ///
+    /// Returns an error if the background thread can't be spawned.
+    pub fn new(resolution: Duration) -> io::Result<Self> {
+        let now = Arc::new(AtomicU64::new(SystemClock.now()));
+
+        let ticker = Arc::downgrade(&now);
+
+        thread::Builder::new()
+            .name("metricrs-coarse-clock".into())
+            .spawn(move || {
+                while let Some(now) = ticker.upgrade() {
+                    now.store(SystemClock.now(), Ordering::Relaxed);
+                    drop(now);
+                    thread::sleep(resolution);
+                }
+            })?;
+
+        Ok(Self { now })
+    }
+}
+
+impl Clock for CoarseClock {
+    #[inline]
+    fn now(&self) -> u64 {
+        self.now.load(Ordering::Relaxed)
+    }
+}
+
+/// A clock only moved by hand, so tests can assert exact durations.
+#[derive(Debug, Default)]
+pub struct MockClock {
+    now: AtomicU64,
+}
+
+impl MockClock {
+    /// Create a new clock at time `0`.
+    pub const fn new() -> Self {
+        Self {
+            now: AtomicU64::new(0),
+        }
+    }
+
+    /// Set the current time in nanoseconds.
+    pub fn set(&self, nanos: u64) {
+        self.now.store(nanos, Ordering::Relaxed);
+    }
+
+    /// Move the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        self.now
+            .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+impl Clock for MockClock {
+    #[inline]
+    fn now(&self) -> u64 {
+        self.now.load(Ordering::Relaxed)
+    }
+}
+
+impl<C: Clock> Clock for LazyLock<C> {
+    #[inline]
+    fn now(&self) -> u64 {
+        LazyLock::force(self).now()
+    }
+}
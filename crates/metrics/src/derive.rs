@@ -1,6 +1,8 @@
 pub use metricrs_derive::*;
 
-use crate::Token;
+use std::borrow::Cow;
+
+use crate::{Labels, Token};
 
 /// Kind of the `#[metricrs::instrument]` driving.
 pub enum DeriveKind {
@@ -21,13 +23,54 @@ pub struct DeriveOption<'a> {
     pub name: Option<&'a str>,
     /// Attach labels to this instrument.
     pub labels: Option<&'a [(&'a str, &'a str)]>,
+    /// Path of the instrumented function, the default `name`.
+    pub path: Option<&'a str>,
+    /// Suffix appended to the `name`, set by `#[metricrs::instrument(kinds(..))]`.
+    pub suffix: Option<&'a str>,
+    /// Value of the `outcome` label, set by `#[metricrs::instrument(outcome = true)]`.
+    pub outcome: Option<&'a str>,
 }
 
 impl<'a> From<DeriveOption<'a>> for Token<'a> {
     fn from(value: DeriveOption<'a>) -> Self {
-        Self::new(
-            value.name.unwrap_or(concat!(module_path!(), column!())),
-            value.labels.unwrap_or_default(),
-        )
+        let name: Cow<'a, str> = match (value.name.or(value.path), value.suffix) {
+            (Some(name), Some(suffix)) => format!("{}{}", name, suffix).into(),
+            (name, _) => name.unwrap_or_default().into(),
+        };
+        let labels = value.labels.unwrap_or_default();
+
+        match value.outcome {
+            Some(outcome) => Self::new(
+                name,
+                labels
+                    .iter()
+                    .copied()
+                    .chain([("outcome", outcome)])
+                    .collect::<Labels>(),
+            ),
+            None => Self::new(name, labels),
+        }
     }
 }
+
+/// Returns the path of a function, e.g. `my_crate::net::LoopFetch::fetch_once`,
+/// from the [`type_name`](std::any::type_name) of a fn item declared in its body.
+pub fn function_path(type_name: &'static str) -> &'static str {
+    let mut path = type_name
+        .rsplit_once("::")
+        .map_or(type_name, |(path, _)| path);
+
+    // the body of `async fn` is a closure.
+    while let Some(parent) = path.strip_suffix("::{{closure}}") {
+        path = parent;
+    }
+
+    path
+}
+
+/// Calls the `body` of an instrumented function once and returns its result.
+///
+/// The closure is `FnOnce`, so the body may return borrows of the captured arguments.
+pub fn call_once<R>(body: impl FnOnce() -> R) -> R {
+    body()
+}
@@ -0,0 +1,46 @@
+use std::fmt::Display;
+
+/// Error returned by the fallible registration methods of [`Registry`](crate::Registry).
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The instrument `name` is already registered as another kind.
+    KindConflict(String),
+    /// The `hash` of instrument `name` collides with the registered instrument `other`.
+    HashCollision { name: String, other: String },
+    /// The registry can't hold more instruments.
+    CardinalityOverflow,
+    /// The instrument `name` is invalid.
+    InvalidName(String),
+    /// Other backend specific error.
+    Other(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::KindConflict(name) => {
+                write!(
+                    f,
+                    "instrument `{}` is already registered as another kind",
+                    name
+                )
+            }
+            Error::HashCollision { name, other } => {
+                write!(f, "instrument `{}` hash collides with `{}`", name, other)
+            }
+            Error::CardinalityOverflow => write!(f, "too many instruments"),
+            Error::InvalidName(name) => write!(f, "invalid instrument name `{}`", name),
+            Error::Other(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Other(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
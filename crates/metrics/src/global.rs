@@ -1,14 +1,18 @@
 //! Funcs to handle global `Registry` instance.
 
-use std::sync::OnceLock;
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{LazyLock, OnceLock, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
 
-use crate::Registry;
+use crate::{Counter, DISABLED, Gauge, Histogram, LabelSet, Labels, Registry, Token, TokenHasher};
 
 static GLOBAL_REGISTRY: OnceLock<Box<dyn Registry>> = OnceLock::new();
 
 /// Set the **global** measuring instruments registry.
 ///
-/// *You should call this function before calling any measuring funs.*
+/// Instrument [`Callsite`]s reached before this call start measuring once the registry is set.
 pub fn set_global_registry<R: Registry + 'static>(registry: R) -> Result<(), Box<dyn Registry>> {
     GLOBAL_REGISTRY.set(Box::new(registry))
 }
@@ -19,3 +23,437 @@ pub fn set_global_registry<R: Registry + 'static>(registry: R) -> Result<(), Box
 pub fn get_global_registry() -> Option<&'static dyn Registry> {
     GLOBAL_REGISTRY.get().map(|v| v.as_ref())
 }
+
+/// Resolves the registry a [`Callsite`] binds to, returns `None` if it is not available yet.
+pub type RegistryResolver = fn() -> Option<&'static dyn Registry>;
+
+/// A **static** registry instance that callsites can target instead of the **global** one,
+/// e.g. with `#[instrument(registry = MY_REGISTRY)]`.
+pub trait StaticRegistry: Sync {
+    /// Returns the registry, or `None` if it is not initialized yet.
+    fn registry(&'static self) -> Option<&'static dyn Registry>;
+}
+
+impl<R: Registry + 'static> StaticRegistry for R {
+    fn registry(&'static self) -> Option<&'static dyn Registry> {
+        Some(self)
+    }
+}
+
+impl<R: Registry + 'static> StaticRegistry for LazyLock<R> {
+    fn registry(&'static self) -> Option<&'static dyn Registry> {
+        Some(LazyLock::force(self) as &dyn Registry)
+    }
+}
+
+impl<R: Registry + 'static> StaticRegistry for OnceLock<R> {
+    fn registry(&'static self) -> Option<&'static dyn Registry> {
+        self.get().map(|registry| registry as &dyn Registry)
+    }
+}
+
+/// A measuring instrument of one callsite, which binds to the **global** registry lazily.
+///
+/// Unlike a `LazyLock`, nothing is cached until the global registry is set, so callsites
+/// reached before [`set_global_registry`] start measuring once it is installed.
+/// After binding, [`get`](Self::get) costs a single atomic load.
+///
+/// Use [`with_registry`](Self::with_registry) to bind to another registry.
+pub struct Callsite<T> {
+    instrument: OnceLock<T>,
+    resolve: RegistryResolver,
+    register: fn(&'static dyn Registry) -> T,
+}
+
+impl<T> Callsite<T> {
+    /// Create a new callsite, `register` is called once to register the instrument.
+    pub const fn new(register: fn(&'static dyn Registry) -> T) -> Self {
+        Self::with_registry(get_global_registry, register)
+    }
+
+    /// Create a new callsite binds to the registry returned by `resolve`,
+    /// `register` is called once to register the instrument.
+    pub const fn with_registry(
+        resolve: RegistryResolver,
+        register: fn(&'static dyn Registry) -> T,
+    ) -> Self {
+        Self {
+            instrument: OnceLock::new(),
+            resolve,
+            register,
+        }
+    }
+
+    /// Returns the instrument, or `None` if the registry is not available yet.
+    #[inline]
+    pub fn get(&self) -> Option<&T> {
+        if DISABLED {
+            return None;
+        }
+
+        match self.instrument.get() {
+            Some(instrument) => Some(instrument),
+            None => self.bind(),
+        }
+    }
+
+    #[cold]
+    fn bind(&self) -> Option<&T> {
+        let registry = (self.resolve)()?;
+
+        Some(self.instrument.get_or_init(|| (self.register)(registry)))
+    }
+}
+
+/// Label value of the series shared by the value sets exceeding the `limit` of a [`LabeledCallsite`].
+pub const OVERFLOW_LABEL_VALUE: &str = "other";
+
+/// Instruments of label value sets with the same hash.
+type Bucket<T> = Vec<(Box<[String]>, T)>;
+
+/// Registered instruments of a [`LabeledCallsite`], indexed by the hash of label values.
+struct LabeledInstruments<T> {
+    buckets: HashMap<u64, Bucket<T>>,
+    len: usize,
+}
+
+impl<T: Clone> LabeledInstruments<T> {
+    /// Returns the hash of label `values`.
+    fn hash(values: &[&str]) -> u64 {
+        let mut hasher = TokenHasher::new();
+
+        for value in values {
+            hasher.write_name(value);
+        }
+
+        hasher.finish()
+    }
+
+    fn get(&self, hash: u64, values: &[&str]) -> Option<&T> {
+        self.buckets
+            .get(&hash)?
+            .iter()
+            .find(|(key, _)| key.iter().eq(values))
+            .map(|(_, instrument)| instrument)
+    }
+
+    fn insert(&mut self, hash: u64, values: &[&str], instrument: T) -> T {
+        self.buckets.entry(hash).or_default().push((
+            values.iter().map(|value| value.to_string()).collect(),
+            instrument.clone(),
+        ));
+
+        instrument
+    }
+
+    /// Returns the instrument of label `values`, `register` is called once per distinct set
+    /// of values up to `limit` sets, further sets share the [`OVERFLOW_LABEL_VALUE`] series.
+    /// The first `keep` values are not replaced in the shared series.
+    fn get_or_register<F>(
+        lock: &RwLock<Self>,
+        values: &[&str],
+        keep: usize,
+        limit: usize,
+        register: F,
+    ) -> T
+    where
+        F: Fn(&[&str]) -> T,
+    {
+        let hash = Self::hash(values);
+
+        if let Some(instrument) = Self::read(lock).get(hash, values) {
+            return instrument.clone();
+        }
+
+        Self::bind(lock, hash, values, keep, limit, register)
+    }
+
+    /// `register` is called without holding the lock, so a panicking registry can't poison it
+    /// and registries may take their time. Racing registrations of the same values keep the first.
+    #[cold]
+    fn bind<F>(
+        lock: &RwLock<Self>,
+        hash: u64,
+        values: &[&str],
+        keep: usize,
+        limit: usize,
+        register: F,
+    ) -> T
+    where
+        F: Fn(&[&str]) -> T,
+    {
+        let overflow = Self::read(lock).len >= limit;
+
+        let (hash, values) = if overflow {
+            let keep = keep.min(values.len());
+
+            let overflow = values[..keep]
+                .iter()
+                .copied()
+                .chain(std::iter::repeat_n(
+                    OVERFLOW_LABEL_VALUE,
+                    values.len() - keep,
+                ))
+                .collect::<Vec<_>>();
+
+            (Self::hash(&overflow), overflow)
+        } else {
+            (hash, values.to_vec())
+        };
+
+        if let Some(instrument) = Self::read(lock).get(hash, &values) {
+            return instrument.clone();
+        }
+
+        let instrument = register(&values);
+
+        let mut instruments = Self::write(lock);
+
+        if let Some(instrument) = instruments.get(hash, &values) {
+            return instrument.clone();
+        }
+
+        if !overflow {
+            // the limit was reached by racing registrations.
+            if instruments.len >= limit {
+                drop(instruments);
+                return Self::bind(lock, hash, &values, keep, limit, register);
+            }
+
+            instruments.len += 1;
+        }
+
+        instruments.insert(hash, &values, instrument)
+    }
+
+    /// Locks for reading, a lock poisoned by a panic is still consistent, every insert
+    /// is a single push.
+    fn read(lock: &RwLock<Self>) -> RwLockReadGuard<'_, Self> {
+        lock.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Locks for writing, see [`read`](Self::read).
+    fn write(lock: &RwLock<Self>) -> RwLockWriteGuard<'_, Self> {
+        lock.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns an empty set of instruments.
+    fn empty() -> RwLock<Self> {
+        RwLock::new(Self {
+            buckets: HashMap::new(),
+            len: 0,
+        })
+    }
+}
+
+/// A measuring instrument of one callsite with runtime label values, which binds to the
+/// **global** registry lazily, or to the registry of [`with_registry`](Self::with_registry).
+///
+/// One instrument is registered and cached per distinct set of label values, up to `limit`
+/// sets. Further sets share one series whose label values are all [`OVERFLOW_LABEL_VALUE`],
+/// so the cardinality of a callsite is bounded.
+pub struct LabeledCallsite<T> {
+    instruments: LazyLock<RwLock<LabeledInstruments<T>>>,
+    resolve: RegistryResolver,
+    register: fn(&'static dyn Registry, &[&str]) -> T,
+    limit: usize,
+    keep: usize,
+}
+
+impl<T: Clone> LabeledCallsite<T> {
+    /// Default `limit` of distinct label value sets.
+    pub const DEFAULT_LIMIT: usize = 64;
+
+    /// Create a new callsite, `register` is called once per distinct set of label values
+    /// to register the instrument.
+    pub const fn new(limit: usize, register: fn(&'static dyn Registry, &[&str]) -> T) -> Self {
+        Self::with_registry(get_global_registry, limit, register)
+    }
+
+    /// Create a new callsite binds to the registry returned by `resolve`, `register` is called
+    /// once per distinct set of label values to register the instrument.
+    pub const fn with_registry(
+        resolve: RegistryResolver,
+        limit: usize,
+        register: fn(&'static dyn Registry, &[&str]) -> T,
+    ) -> Self {
+        Self {
+            instruments: LazyLock::new(LabeledInstruments::empty),
+            resolve,
+            register,
+            limit,
+            keep: 0,
+        }
+    }
+
+    /// Keep the first `keep` values in the series shared by the value sets exceeding the `limit`,
+    /// e.g. the instrument name and label keys passed as values by [`counter!`](crate::counter).
+    pub const fn keep_on_overflow(mut self, keep: usize) -> Self {
+        self.keep = keep;
+        self
+    }
+
+    /// Returns the instrument of label `values`, or `None` if the registry is not available yet.
+    pub fn get(&self, values: &[&str]) -> Option<T> {
+        if DISABLED {
+            return None;
+        }
+
+        let registry = (self.resolve)()?;
+
+        Some(LabeledInstruments::get_or_register(
+            &self.instruments,
+            values,
+            self.keep,
+            self.limit,
+            |values| (self.register)(registry, values),
+        ))
+    }
+}
+
+/// Create the token of the instrument cached by the `counter!`-style macros under
+/// `[name, keys.., values..]`, e.g. `["http.requests", "method", "GET"]`.
+pub fn macro_token<'a>(values: &[&'a str]) -> Token<'a> {
+    let (name, labels) = values.split_first().expect("instrument name");
+    let (keys, values) = labels.split_at(labels.len() / 2);
+
+    Token::new(
+        *name,
+        keys.iter()
+            .copied()
+            .zip(values.iter().copied())
+            .collect::<Labels>(),
+    )
+}
+
+/// Returns true if `a` and `b` are the same label keys.
+const fn same_keys(a: &[&str], b: &[&str]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut i = 0;
+
+    while i < a.len() {
+        let (key, other) = (a[i].as_bytes(), b[i].as_bytes());
+
+        if key.len() != other.len() {
+            return false;
+        }
+
+        let mut j = 0;
+
+        while j < key.len() {
+            if key[j] != other[j] {
+                return false;
+            }
+
+            j += 1;
+        }
+
+        i += 1;
+    }
+
+    true
+}
+
+/// A family of measuring instruments with the same `name` and typed label sets, which binds
+/// to the **global** registry lazily, or to the registry of [`with_registry`](Self::with_registry).
+///
+/// One instrument is registered and cached per distinct [`LabelSet`], up to `limit` sets.
+/// Further sets share one series whose label values are all [`OVERFLOW_LABEL_VALUE`].
+/// Looking up a cached instrument does not allocate.
+pub struct Family<L, T> {
+    name: &'static str,
+    instruments: LazyLock<RwLock<LabeledInstruments<T>>>,
+    resolve: RegistryResolver,
+    register: fn(&'static dyn Registry, Token<'_>) -> T,
+    limit: usize,
+    label_set: PhantomData<fn(&L)>,
+}
+
+impl<L: LabelSet, T: Clone> Family<L, T> {
+    /// Create a new family, `register` is called once per distinct label set
+    /// to register the instrument.
+    pub const fn new(
+        name: &'static str,
+        register: fn(&'static dyn Registry, Token<'_>) -> T,
+    ) -> Self {
+        Self::with_registry(get_global_registry, name, register)
+    }
+
+    /// Create a new family binds to the registry returned by `resolve`, `register` is called
+    /// once per distinct label set to register the instrument.
+    pub const fn with_registry(
+        resolve: RegistryResolver,
+        name: &'static str,
+        register: fn(&'static dyn Registry, Token<'_>) -> T,
+    ) -> Self {
+        Self {
+            name,
+            instruments: LazyLock::new(LabeledInstruments::empty),
+            resolve,
+            register,
+            limit: LabeledCallsite::<T>::DEFAULT_LIMIT,
+            label_set: PhantomData,
+        }
+    }
+
+    /// Set the `limit` of distinct label sets.
+    pub const fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Returns the instrument of `labels`, or `None` if the registry is not available yet.
+    ///
+    /// `labels` may be any [`LabelSet`] with the same [`KEYS`](LabelSet::KEYS) as `L`,
+    /// e.g. `L` with a shorter lifetime, so label values can borrow local data.
+    pub fn get<S: LabelSet>(&self, labels: &S) -> Option<T> {
+        const {
+            assert!(
+                same_keys(S::KEYS, L::KEYS),
+                "label set keys must be the same as the keys of the family."
+            )
+        };
+
+        if DISABLED {
+            return None;
+        }
+
+        let registry = (self.resolve)()?;
+
+        Some(LabeledInstruments::get_or_register(
+            &self.instruments,
+            labels.label_values().as_ref(),
+            0,
+            self.limit,
+            |values| {
+                let labels = L::KEYS.iter().copied().zip(values.iter().copied());
+
+                (self.register)(registry, Token::new(self.name, labels.collect::<Labels>()))
+            },
+        ))
+    }
+}
+
+impl<L: LabelSet> Family<L, Counter> {
+    /// Create a new family of counters.
+    pub const fn counter(name: &'static str) -> Self {
+        Self::new(name, |registry, token| registry.counter(token))
+    }
+}
+
+impl<L: LabelSet> Family<L, Gauge> {
+    /// Create a new family of gauges.
+    pub const fn gauge(name: &'static str) -> Self {
+        Self::new(name, |registry, token| registry.gauge(token))
+    }
+}
+
+impl<L: LabelSet> Family<L, Histogram> {
+    /// Create a new family of histograms.
+    pub const fn histogram(name: &'static str) -> Self {
+        Self::new(name, |registry, token| registry.histogam(token))
+    }
+}
@@ -0,0 +1,99 @@
+use std::{borrow::Cow, rc::Rc, sync::Arc};
+
+use crate::{Labels, TokenHasher};
+
+/// A value of a label in a [`LabelSet`].
+///
+/// Implemented for string types and `bool`, derive it for a fieldless enum with
+/// `#[derive(metricrs::LabelSet)]`, each variant becomes a value.
+pub trait LabelValue {
+    /// Returns the label value.
+    fn label_value(&self) -> &str;
+}
+
+impl LabelValue for str {
+    fn label_value(&self) -> &str {
+        self
+    }
+}
+
+impl LabelValue for String {
+    fn label_value(&self) -> &str {
+        self
+    }
+}
+
+impl LabelValue for Cow<'_, str> {
+    fn label_value(&self) -> &str {
+        self
+    }
+}
+
+impl LabelValue for Box<str> {
+    fn label_value(&self) -> &str {
+        self
+    }
+}
+
+impl LabelValue for Rc<str> {
+    fn label_value(&self) -> &str {
+        self
+    }
+}
+
+impl LabelValue for Arc<str> {
+    fn label_value(&self) -> &str {
+        self
+    }
+}
+
+impl LabelValue for bool {
+    fn label_value(&self) -> &str {
+        if *self { "true" } else { "false" }
+    }
+}
+
+impl<T: LabelValue + ?Sized> LabelValue for &T {
+    fn label_value(&self) -> &str {
+        (**self).label_value()
+    }
+}
+
+/// A typed set of label pairs, usually implemented with `#[derive(metricrs::LabelSet)]`.
+///
+/// The fields of a struct become label keys, a fieldless enum is a set of one label
+/// whose value is the variant.
+pub trait LabelSet {
+    /// Label keys, sorted without duplicates.
+    const KEYS: &'static [&'static str];
+
+    /// Returns label values in the order of [`KEYS`](Self::KEYS).
+    fn label_values(&self) -> impl AsRef<[&str]>;
+
+    /// Returns the label pairs, e.g. to create a [`Token`](crate::Token).
+    ///
+    /// Pairs borrow keys and values, but are collected into a new `Vec`,
+    /// use [`token_hash`](Self::token_hash) to only hash them.
+    fn labels(&self) -> Labels<'_> {
+        Self::KEYS
+            .iter()
+            .copied()
+            .zip(self.label_values().as_ref().iter().copied())
+            .collect()
+    }
+
+    /// Returns the `hash` of the [`Token`](crate::Token) of `name` with these labels.
+    ///
+    /// Keys and values are fed into [`TokenHasher`] directly, without building the labels.
+    fn token_hash(&self, name: &str) -> u64 {
+        let mut hasher = TokenHasher::new();
+
+        hasher.write_name(name);
+
+        for (key, value) in Self::KEYS.iter().zip(self.label_values().as_ref()) {
+            hasher.write_label(key, value);
+        }
+
+        hasher.finish()
+    }
+}
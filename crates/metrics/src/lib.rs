@@ -1,14 +1,49 @@
 //! A lightweight metrics facade for `Rust`.
+//!
+//! # Features
+//!
+//! - `disabled`: compiles out all instrumentation, `#[instrument]` emits the original
+//!   function and instrument methods are no-ops. Cargo unifies features across the whole
+//!   dependency graph, so any crate enabling `disabled` turns metrics off for the whole build.
+//!   Libraries should never enable it, only the final binary crate, e.g. behind its own feature.
 
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+/// True if the `disabled` feature compiles out all instrumentation.
+pub(crate) const DISABLED: bool = cfg!(feature = "disabled");
+
 mod registry;
 pub use registry::*;
 
+mod error;
+pub use error::*;
+
+mod atomic;
+pub use atomic::*;
+
+mod outcome;
+pub use outcome::*;
+
+mod clock;
+pub use clock::*;
+
+mod sample;
+pub use sample::*;
+
+mod metrics;
+pub use metrics::*;
+
+mod label_set;
+pub use label_set::*;
+
 #[cfg(feature = "global")]
 #[cfg_attr(docsrs, doc(cfg(feature = "global")))]
 pub mod global;
 
+#[cfg(feature = "global")]
+#[cfg_attr(docsrs, doc(cfg(feature = "global")))]
+mod macros;
+
 #[cfg(feature = "derive")]
 #[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
 mod derive;
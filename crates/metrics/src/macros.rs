@@ -0,0 +1,152 @@
+//! Function-like macros to get measuring instruments from the **global** registry.
+
+/// Get a [`Counter`](crate::Counter) from the **global** registry.
+///
+/// The macro evaluates to a `&'static Counter` cached per callsite.
+///
+/// When the `name` and all label values are string literals, the instrument is registered
+/// once (see [`Callsite`](crate::global::Callsite)). Otherwise, `name`, keys and values may be
+/// any expression of a type implementing `AsRef<str>`, and one instrument is registered per
+/// distinct set of them (see [`LabeledCallsite`](crate::global::LabeledCallsite)), up to
+/// [`DEFAULT_LIMIT`](crate::global::LabeledCallsite::DEFAULT_LIMIT) sets. Further sets share
+/// the series whose label values are all [`OVERFLOW_LABEL_VALUE`](crate::global::OVERFLOW_LABEL_VALUE).
+///
+/// ```no_run
+/// let method = String::from("GET");
+///
+/// metricrs::counter!("http.requests", "method" => "GET").increment(1);
+/// metricrs::counter!("http.requests", "method" => method).increment(1);
+/// ```
+#[macro_export]
+macro_rules! counter {
+    ($name:literal $(, $key:literal => $value:literal)* $(,)?) => {{
+        const TOKEN: $crate::Token<'static> = $crate::Token::borrowed(
+            $name,
+            &$crate::canonical_labels([$(($key, $value)),*]),
+        );
+
+        static COUNTER: $crate::global::Callsite<$crate::Counter> =
+            $crate::global::Callsite::new(|registry| registry.counter(TOKEN));
+
+        static NOOP: $crate::Counter = $crate::Counter::Noop;
+
+        COUNTER.get().unwrap_or(&NOOP)
+    }};
+    ($name:expr $(, $key:expr => $value:expr)* $(,)?) => {{
+        static COUNTER: $crate::global::LabeledCallsite<&'static $crate::Counter> =
+            $crate::global::LabeledCallsite::new(
+                $crate::global::LabeledCallsite::<&'static $crate::Counter>::DEFAULT_LIMIT,
+                |registry, values| {
+                    // bounded by the limit, so leaking gives a `&'static` like the literal callsite.
+                    &*::std::boxed::Box::leak(::std::boxed::Box::new(
+                        registry.counter($crate::global::macro_token(values)),
+                    ))
+                },
+            )
+            .keep_on_overflow(1 + <[&str]>::len(&[$(stringify!($key)),*]));
+
+        static NOOP: $crate::Counter = $crate::Counter::Noop;
+
+        COUNTER
+            .get(&[
+                ::std::convert::AsRef::<str>::as_ref(&$name),
+                $(::std::convert::AsRef::<str>::as_ref(&$key),)*
+                $(::std::convert::AsRef::<str>::as_ref(&$value),)*
+            ])
+            .unwrap_or(&NOOP)
+    }};
+}
+
+/// Get a [`Gauge`](crate::Gauge) from the **global** registry.
+///
+/// See [`counter!`](crate::counter) for the caching rules.
+///
+/// ```no_run
+/// metricrs::gauge!("http.in_flight", "method" => "GET").increment(1.0);
+/// ```
+#[macro_export]
+macro_rules! gauge {
+    ($name:literal $(, $key:literal => $value:literal)* $(,)?) => {{
+        const TOKEN: $crate::Token<'static> = $crate::Token::borrowed(
+            $name,
+            &$crate::canonical_labels([$(($key, $value)),*]),
+        );
+
+        static GAUGE: $crate::global::Callsite<$crate::Gauge> =
+            $crate::global::Callsite::new(|registry| registry.gauge(TOKEN));
+
+        static NOOP: $crate::Gauge = $crate::Gauge::Noop;
+
+        GAUGE.get().unwrap_or(&NOOP)
+    }};
+    ($name:expr $(, $key:expr => $value:expr)* $(,)?) => {{
+        static GAUGE: $crate::global::LabeledCallsite<&'static $crate::Gauge> =
+            $crate::global::LabeledCallsite::new(
+                $crate::global::LabeledCallsite::<&'static $crate::Gauge>::DEFAULT_LIMIT,
+                |registry, values| {
+                    // bounded by the limit, so leaking gives a `&'static` like the literal callsite.
+                    &*::std::boxed::Box::leak(::std::boxed::Box::new(
+                        registry.gauge($crate::global::macro_token(values)),
+                    ))
+                },
+            )
+            .keep_on_overflow(1 + <[&str]>::len(&[$(stringify!($key)),*]));
+
+        static NOOP: $crate::Gauge = $crate::Gauge::Noop;
+
+        GAUGE
+            .get(&[
+                ::std::convert::AsRef::<str>::as_ref(&$name),
+                $(::std::convert::AsRef::<str>::as_ref(&$key),)*
+                $(::std::convert::AsRef::<str>::as_ref(&$value),)*
+            ])
+            .unwrap_or(&NOOP)
+    }};
+}
+
+/// Get a [`Histogram`](crate::Histogram) from the **global** registry.
+///
+/// See [`counter!`](crate::counter) for the caching rules.
+///
+/// ```no_run
+/// metricrs::histogram!("http.latency", "method" => "GET").record(0.1);
+/// ```
+#[macro_export]
+macro_rules! histogram {
+    ($name:literal $(, $key:literal => $value:literal)* $(,)?) => {{
+        const TOKEN: $crate::Token<'static> = $crate::Token::borrowed(
+            $name,
+            &$crate::canonical_labels([$(($key, $value)),*]),
+        );
+
+        static HISTOGRAM: $crate::global::Callsite<$crate::Histogram> =
+            $crate::global::Callsite::new(|registry| registry.histogam(TOKEN));
+
+        static NOOP: $crate::Histogram = $crate::Histogram::Noop;
+
+        HISTOGRAM.get().unwrap_or(&NOOP)
+    }};
+    ($name:expr $(, $key:expr => $value:expr)* $(,)?) => {{
+        static HISTOGRAM: $crate::global::LabeledCallsite<&'static $crate::Histogram> =
+            $crate::global::LabeledCallsite::new(
+                $crate::global::LabeledCallsite::<&'static $crate::Histogram>::DEFAULT_LIMIT,
+                |registry, values| {
+                    // bounded by the limit, so leaking gives a `&'static` like the literal callsite.
+                    &*::std::boxed::Box::leak(::std::boxed::Box::new(
+                        registry.histogam($crate::global::macro_token(values)),
+                    ))
+                },
+            )
+            .keep_on_overflow(1 + <[&str]>::len(&[$(stringify!($key)),*]));
+
+        static NOOP: $crate::Histogram = $crate::Histogram::Noop;
+
+        HISTOGRAM
+            .get(&[
+                ::std::convert::AsRef::<str>::as_ref(&$name),
+                $(::std::convert::AsRef::<str>::as_ref(&$key),)*
+                $(::std::convert::AsRef::<str>::as_ref(&$value),)*
+            ])
+            .unwrap_or(&NOOP)
+    }};
+}
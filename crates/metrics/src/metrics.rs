@@ -0,0 +1,110 @@
+use crate::{Counter, Gauge, Histogram, Registry, Token};
+
+/// Kind of an instrument in a [`Metrics`] struct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    /// A [`Counter`](crate::Counter) instrument.
+    Counter,
+    /// A [`Gauge`](crate::Gauge) instrument.
+    Gauge,
+    /// A [`Histogram`](crate::Histogram) instrument.
+    Histogram,
+}
+
+/// Description of one instrument in a [`Metrics`] struct, exporters can use it
+/// to publish the `help` and `unit` which a [`Token`](crate::Token) does not carry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricDescription {
+    /// Full instrument `name`, including the prefix.
+    pub name: String,
+    /// Instrument kind.
+    pub kind: MetricKind,
+    /// Help text of the instrument.
+    pub help: Option<&'static str>,
+    /// Unit of the recorded values.
+    pub unit: Option<&'static str>,
+}
+
+/// A struct of measuring instruments registered together, usually implemented
+/// with `#[derive(metricrs::Metrics)]`.
+pub trait Metrics: Sized {
+    /// Register all instruments into `registry`, names are prefixed with `prefix`.
+    fn register(registry: &dyn Registry, prefix: &str) -> Self;
+
+    /// Returns the descriptions of all instruments, names are prefixed with `prefix`.
+    fn describe(prefix: &str) -> Vec<MetricDescription>;
+}
+
+/// A field of a [`Metrics`] struct, implemented for [`Counter`], [`Gauge`], [`Histogram`]
+/// and nested [`Metrics`] structs.
+pub trait MetricField: Sized {
+    /// True for nested [`Metrics`] structs, which don't take labels, `help` or `unit`.
+    const NESTED: bool = false;
+
+    /// Register the field as `name` into `registry`.
+    fn register_field(registry: &dyn Registry, name: &str, labels: &[(&str, &str)]) -> Self;
+
+    /// Returns the descriptions of the field named `name`.
+    fn describe_field(
+        name: &str,
+        help: Option<&'static str>,
+        unit: Option<&'static str>,
+    ) -> Vec<MetricDescription>;
+}
+
+macro_rules! instrument_field {
+    ($ty:ident, $register:ident) => {
+        impl MetricField for $ty {
+            fn register_field(
+                registry: &dyn Registry,
+                name: &str,
+                labels: &[(&str, &str)],
+            ) -> Self {
+                registry.$register(Token::new(name, labels))
+            }
+
+            fn describe_field(
+                name: &str,
+                help: Option<&'static str>,
+                unit: Option<&'static str>,
+            ) -> Vec<MetricDescription> {
+                vec![MetricDescription {
+                    name: name.to_owned(),
+                    kind: MetricKind::$ty,
+                    help,
+                    unit,
+                }]
+            }
+        }
+    };
+}
+
+instrument_field!(Counter, counter);
+instrument_field!(Gauge, gauge);
+instrument_field!(Histogram, histogam);
+
+impl<T: Metrics> MetricField for T {
+    const NESTED: bool = true;
+
+    fn register_field(registry: &dyn Registry, name: &str, _: &[(&str, &str)]) -> Self {
+        T::register(registry, name)
+    }
+
+    fn describe_field(
+        name: &str,
+        _: Option<&'static str>,
+        _: Option<&'static str>,
+    ) -> Vec<MetricDescription> {
+        T::describe(name)
+    }
+}
+
+/// Returns the instrument `name` under `prefix`, e.g. `http.server` and `requests`
+/// are joined into `http.server.requests`.
+pub fn metric_name(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}.{}", prefix, name)
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{Clock, Counter, DISABLED, Histogram, TimeUnit, clock::Stopwatch};
+
+/// Return values whose outcome is recorded by `#[instrument(outcome = true)]`.
+pub trait Outcome {
+    /// Returns true if this value is a success.
+    fn is_ok(&self) -> bool;
+}
+
+impl<T, E> Outcome for Result<T, E> {
+    #[inline]
+    fn is_ok(&self) -> bool {
+        Result::is_ok(self)
+    }
+}
+
+/// Measuring instruments which record a call on completion, see [`Outcomes`].
+pub trait OutcomeRecord {
+    /// Records a call lasted `elapsed`, which stands for `weight` calls.
+    fn record_call(&self, elapsed: f64, weight: u64);
+}
+
+impl OutcomeRecord for Counter {
+    /// Counts the call `weight` times.
+    #[inline]
+    fn record_call(&self, _: f64, weight: u64) {
+        self.increment(weight);
+    }
+}
+
+impl OutcomeRecord for Histogram {
+    /// Records the elapsed time of the call.
+    #[inline]
+    fn record_call(&self, elapsed: f64, _: u64) {
+        self.record(elapsed);
+    }
+}
+
+/// A pair of measuring instruments, one for successful calls and one for failed calls,
+/// usually registered with an `outcome="ok"` and an `outcome="err"` label.
+///
+/// Cloning is cheap, clones write to the same underlying series.
+#[derive(Clone)]
+pub struct Outcomes<T> {
+    /// Instrument for successful calls.
+    pub ok: T,
+    /// Instrument for failed calls.
+    pub err: T,
+}
+
+impl<T: OutcomeRecord> Outcomes<T> {
+    /// Start a call, returns a guard which records into `ok` or `err` by the outcome
+    /// passed to [`finish`](OutcomeGuard::finish), timing instruments record the elapsed seconds.
+    ///
+    /// If the guard is dropped without finishing, e.g. on panic or future cancellation,
+    /// the call is recorded into `err`.
+    #[inline]
+    pub fn start(&self) -> OutcomeGuard<'_, T> {
+        OutcomeGuard {
+            outcomes: self,
+            weight: 1,
+            start: (!DISABLED).then(Stopwatch::start),
+        }
+    }
+
+    /// Like [`start`](Self::start), but timing instruments read `clock` and record the elapsed time in `unit`.
+    #[inline]
+    pub fn start_with<'a>(&'a self, clock: &'a dyn Clock, unit: TimeUnit) -> OutcomeGuard<'a, T> {
+        OutcomeGuard {
+            outcomes: self,
+            weight: 1,
+            start: (!DISABLED).then(|| Stopwatch::start_with(clock, unit)),
+        }
+    }
+}
+
+/// A guard records a call into [`Outcomes`] when finished or dropped,
+/// created by [`Outcomes::start`].
+#[must_use = "The call is recorded as failed immediately if unused."]
+pub struct OutcomeGuard<'a, T: OutcomeRecord> {
+    outcomes: &'a Outcomes<T>,
+    weight: u64,
+    start: Option<Stopwatch<'a>>,
+}
+
+impl<T: OutcomeRecord> OutcomeGuard<'_, T> {
+    /// Set the number of calls this call stands for, e.g. [`Sampler::weight`](crate::Sampler::weight).
+    #[inline]
+    pub fn weighted(mut self, weight: u64) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Records the call by the outcome of its return value.
+    #[inline]
+    pub fn finish<O: Outcome + ?Sized>(mut self, output: &O) {
+        if let Some(elapsed) = self.elapsed() {
+            if output.is_ok() {
+                self.outcomes.ok.record_call(elapsed, self.weight);
+            } else {
+                self.outcomes.err.record_call(elapsed, self.weight);
+            }
+        }
+    }
+
+    /// Stop the guard, returns the elapsed time in the unit of this guard.
+    fn elapsed(&mut self) -> Option<f64> {
+        Some(self.start.take()?.elapsed())
+    }
+}
+
+impl<T: OutcomeRecord> Drop for OutcomeGuard<'_, T> {
+    fn drop(&mut self) {
+        if let Some(elapsed) = self.elapsed() {
+            self.outcomes.err.record_call(elapsed, self.weight);
+        }
+    }
+}
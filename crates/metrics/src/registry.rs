@@ -1,31 +1,362 @@
 use std::{
+    borrow::Cow,
+    cmp::Ordering,
     fmt::Debug,
-    hash::{DefaultHasher, Hasher},
+    hash::{Hash, Hasher},
+    slice,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering as AtomicOrdering},
+    },
 };
 
+use crate::{
+    AtomicHistogram, Clock, DISABLED, Error, Sampler, TimeUnit, atomic::fetch_update_f64,
+    clock::Stopwatch,
+};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Stable hasher used to calculate [`Token::hash`].
+///
+/// The algorithm is 64-bit `FNV-1a` over the `name` bytes followed by each label's
+/// `key` and `value` bytes, every field terminated by a `0xff` byte (which never
+/// appears in UTF-8). The result does not depend on the process, build or toolchain,
+/// so it can be used as a wire identity.
+#[derive(Debug, Clone, Copy)]
+pub struct TokenHasher(u64);
+
+impl Default for TokenHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TokenHasher {
+    /// Create a new hasher.
+    pub const fn new() -> Self {
+        Self(FNV_OFFSET_BASIS)
+    }
+
+    /// Feed raw `bytes` into this hasher.
+    pub const fn write(&mut self, bytes: &[u8]) {
+        let mut i = 0;
+
+        while i < bytes.len() {
+            self.0 ^= bytes[i] as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+            i += 1;
+        }
+    }
+
+    /// Feed a field, terminated by `0xff`, into this hasher.
+    const fn write_field(&mut self, field: &str) {
+        self.write(field.as_bytes());
+        self.write(&[0xff]);
+    }
+
+    /// Feed the instrument `name` into this hasher.
+    pub const fn write_name(&mut self, name: &str) {
+        self.write_field(name);
+    }
+
+    /// Feed one label pair into this hasher.
+    pub const fn write_label(&mut self, key: &str, value: &str) {
+        self.write_field(key);
+        self.write_field(value);
+    }
+
+    /// Returns the hash value.
+    pub const fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Label pairs attached to a [`Token`], either borrowed or owned.
+#[derive(Debug, Clone)]
+pub enum Labels<'a> {
+    /// Borrowed label pairs, e.g. string literals.
+    Borrowed(&'a [(&'a str, &'a str)]),
+    /// Owned label pairs, e.g. values computed at runtime.
+    Owned(Vec<(Cow<'a, str>, Cow<'a, str>)>),
+}
+
+/// Returns true if `a` is less than `b` in lexicographic byte order.
+const fn str_lt(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+
+    let mut i = 0;
+
+    while i < a.len() && i < b.len() {
+        if a[i] != b[i] {
+            return a[i] < b[i];
+        }
+
+        i += 1;
+    }
+
+    a.len() < b.len()
+}
+
+/// Sort label pairs by key in a `const` context, e.g. to build a canonical label
+/// slice for [`Token::borrowed`].
+///
+/// # Panics
+///
+/// Panics if two label pairs have the same key, which is a compile error when
+/// evaluated in a `const` context.
+pub const fn canonical_labels<'a, const N: usize>(
+    mut labels: [(&'a str, &'a str); N],
+) -> [(&'a str, &'a str); N] {
+    let mut i = 1;
+
+    while i < N {
+        let mut j = i;
+
+        while j > 0 && str_lt(labels[j].0, labels[j - 1].0) {
+            labels.swap(j, j - 1);
+            j -= 1;
+        }
+
+        i += 1;
+    }
+
+    assert!(
+        is_canonical(&labels),
+        "duplicate label keys are not allowed."
+    );
+
+    labels
+}
+
+/// Returns true if `labels` are sorted by key without duplicate keys.
+const fn is_canonical(labels: &[(&str, &str)]) -> bool {
+    let mut i = 1;
+
+    while i < labels.len() {
+        if !str_lt(labels[i - 1].0, labels[i].0) {
+            return false;
+        }
+
+        i += 1;
+    }
+
+    true
+}
+
+impl<'a> Labels<'a> {
+    /// Returns the number of label pairs.
+    pub fn len(&self) -> usize {
+        match self {
+            Labels::Borrowed(labels) => labels.len(),
+            Labels::Owned(labels) => labels.len(),
+        }
+    }
+
+    /// Returns true if there are no label pairs.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over the `(key, value)` label pairs.
+    pub fn iter(&self) -> LabelsIter<'_> {
+        match self {
+            Labels::Borrowed(labels) => LabelsIter::Borrowed(labels.iter()),
+            Labels::Owned(labels) => LabelsIter::Owned(labels.iter()),
+        }
+    }
+
+    /// Returns true if label pairs are sorted by key without duplicate keys.
+    pub fn is_canonical(&self) -> bool {
+        let mut iter = self.iter();
+
+        let Some((mut last, _)) = iter.next() else {
+            return true;
+        };
+
+        for (key, _) in iter {
+            if last >= key {
+                return false;
+            }
+
+            last = key;
+        }
+
+        true
+    }
+
+    /// Sort label pairs by key, for duplicate keys only the last value is kept.
+    ///
+    /// Canonical labels are returned unchanged without allocation.
+    pub fn into_canonical(self) -> Self {
+        if self.is_canonical() {
+            return self;
+        }
+
+        let mut labels = match self {
+            Labels::Borrowed(labels) => labels
+                .iter()
+                .map(|(key, value)| (Cow::Borrowed(*key), Cow::Borrowed(*value)))
+                .collect(),
+            Labels::Owned(labels) => labels,
+        };
+
+        // stable sort, so the last value of duplicate keys stays last.
+        labels.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut canonical: Vec<(Cow<'a, str>, Cow<'a, str>)> = Vec::with_capacity(labels.len());
+
+        for label in labels {
+            match canonical.last_mut() {
+                Some(last) if last.0 == label.0 => *last = label,
+                _ => canonical.push(label),
+            }
+        }
+
+        Labels::Owned(canonical)
+    }
+
+    /// Convert into `Labels` that owns all of its data.
+    pub fn into_owned(self) -> Labels<'static> {
+        Labels::Owned(
+            self.iter()
+                .map(|(key, value)| (Cow::Owned(key.to_owned()), Cow::Owned(value.to_owned())))
+                .collect(),
+        )
+    }
+}
+
+impl Default for Labels<'_> {
+    fn default() -> Self {
+        Labels::Borrowed(&[])
+    }
+}
+
+impl<'a> From<&'a [(&'a str, &'a str)]> for Labels<'a> {
+    fn from(value: &'a [(&'a str, &'a str)]) -> Self {
+        Labels::Borrowed(value)
+    }
+}
+
+impl<'a, const N: usize> From<&'a [(&'a str, &'a str); N]> for Labels<'a> {
+    fn from(value: &'a [(&'a str, &'a str); N]) -> Self {
+        Labels::Borrowed(value)
+    }
+}
+
+impl<'a, K, V> From<Vec<(K, V)>> for Labels<'a>
+where
+    K: Into<Cow<'a, str>>,
+    V: Into<Cow<'a, str>>,
+{
+    fn from(value: Vec<(K, V)>) -> Self {
+        value.into_iter().collect()
+    }
+}
+
+impl<'a, K, V> FromIterator<(K, V)> for Labels<'a>
+where
+    K: Into<Cow<'a, str>>,
+    V: Into<Cow<'a, str>>,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        Labels::Owned(
+            iter.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
+
+impl PartialEq for Labels<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for Labels<'_> {}
+
+impl PartialOrd for Labels<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Labels<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl Hash for Labels<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+
+        for label in self.iter() {
+            label.hash(state);
+        }
+    }
+}
+
+/// Iterator over the `(key, value)` pairs of [`Labels`].
+pub enum LabelsIter<'b> {
+    Borrowed(slice::Iter<'b, (&'b str, &'b str)>),
+    Owned(slice::Iter<'b, (Cow<'b, str>, Cow<'b, str>)>),
+}
+
+impl<'b> Iterator for LabelsIter<'b> {
+    type Item = (&'b str, &'b str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            LabelsIter::Borrowed(iter) => iter.next().map(|(key, value)| (*key, *value)),
+            LabelsIter::Owned(iter) => iter
+                .next()
+                .map(|(key, value)| (key.as_ref(), value.as_ref())),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            LabelsIter::Borrowed(iter) => iter.size_hint(),
+            LabelsIter::Owned(iter) => iter.size_hint(),
+        }
+    }
+}
+
 /// `key` id to reference a Measuring instrument
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
 pub struct Token<'a> {
     pub hash: u64,
     /// Instrument `name`
-    pub name: &'a str,
+    pub name: Cow<'a, str>,
     /// Instrument `labels`
-    pub labels: &'a [(&'a str, &'a str)],
+    pub labels: Labels<'a>,
 }
 
 impl<'a> Token<'a> {
-    /// Create a new `token` and pre-calculate it's `hash` with [`DefaultHasher`].
-    pub fn new(name: &'a str, labels: &'a [(&'a str, &'a str)]) -> Self {
-        let mut hasher = DefaultHasher::new();
+    /// Create a new `token` and pre-calculate it's `hash` with [`TokenHasher`].
+    ///
+    /// Both `name` and `labels` can be either borrowed or owned, so label values
+    /// may come from runtime data.
+    ///
+    /// `labels` are normalized with [`Labels::into_canonical`] before hashing, so the
+    /// same set of label pairs always maps to the same `token`.
+    pub fn new<N, L>(name: N, labels: L) -> Self
+    where
+        N: Into<Cow<'a, str>>,
+        L: Into<Labels<'a>>,
+    {
+        let name = name.into();
+        let labels = labels.into().into_canonical();
 
-        hasher.write(name.as_bytes());
-        hasher.write_u8(0xff);
+        let mut hasher = TokenHasher::new();
 
-        for (key, value) in labels {
-            hasher.write(key.as_bytes());
-            hasher.write_u8(0xff);
-            hasher.write(value.as_bytes());
-            hasher.write_u8(0xff);
+        hasher.write_name(&name);
+
+        for (key, value) in labels.iter() {
+            hasher.write_label(key, value);
         }
 
         Self {
@@ -34,6 +365,48 @@ impl<'a> Token<'a> {
             labels,
         }
     }
+
+    /// Create a new `token` from borrowed data in a `const` context,
+    /// so tokens built from literals are hashed at compile time.
+    ///
+    /// The `hash` is the same as the one calculated by [`new`](Self::new).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `labels` are not sorted by key or contain duplicate keys,
+    /// use [`canonical_labels`] to sort them.
+    pub const fn borrowed(name: &'a str, labels: &'a [(&'a str, &'a str)]) -> Self {
+        assert!(
+            is_canonical(labels),
+            "labels must be sorted by key without duplicate keys."
+        );
+
+        let mut hasher = TokenHasher::new();
+
+        hasher.write_name(name);
+
+        let mut i = 0;
+
+        while i < labels.len() {
+            hasher.write_label(labels[i].0, labels[i].1);
+            i += 1;
+        }
+
+        Self {
+            hash: hasher.finish(),
+            name: Cow::Borrowed(name),
+            labels: Labels::Borrowed(labels),
+        }
+    }
+
+    /// Convert into a `Token` that owns all of its data.
+    pub fn into_owned(self) -> Token<'static> {
+        Token {
+            hash: self.hash,
+            name: Cow::Owned(self.name.into_owned()),
+            labels: self.labels.into_owned(),
+        }
+    }
 }
 
 /// Registry implemenation should implement this trait for `instrument counter`.
@@ -45,27 +418,53 @@ pub trait CounterWrite: Send + Sync {
 }
 
 /// `Counter` measuring instrument.
+///
+/// Cloning is cheap, clones write to the same underlying series.
+#[derive(Clone)]
 pub enum Counter {
     Noop,
-    Record(Box<dyn CounterWrite>),
+    Record(Arc<dyn CounterWrite>),
+    /// A builtin atomic counter, updated without dynamic dispatch.
+    Atomic(Arc<AtomicU64>),
 }
 
 impl Counter {
     /// See [`increment`](InstrumentCounter::increment)
     #[inline]
     pub fn increment(&self, step: u64) {
+        if DISABLED {
+            return;
+        }
+
         match self {
             Counter::Noop => {}
             Counter::Record(raw_counter) => raw_counter.increment(step),
+            Counter::Atomic(value) => {
+                value.fetch_add(step, AtomicOrdering::Relaxed);
+            }
         }
     }
 
     /// See [`absolute`](InstrumentCounter::absolute)
     #[inline]
     pub fn absolute(&self, value: u64) {
+        if DISABLED {
+            return;
+        }
+
         match self {
             Counter::Noop => {}
             Counter::Record(raw_counter) => raw_counter.absolute(value),
+            Counter::Atomic(atomic) => atomic.store(value, AtomicOrdering::Relaxed),
+        }
+    }
+
+    /// Increment counter with `step` for the calls sampled by `sampler`,
+    /// scaled by [`Sampler::weight`] to stay unbiased.
+    #[inline]
+    pub fn increment_sampled(&self, step: u64, sampler: &Sampler) {
+        if sampler.sample() {
+            self.increment(step.saturating_mul(sampler.weight()));
         }
     }
 }
@@ -83,38 +482,80 @@ pub trait GaugeWrite: Send + Sync {
 }
 
 /// `Gauge` measuring instrument.
+///
+/// Cloning is cheap, clones write to the same underlying series.
+#[derive(Clone)]
 pub enum Gauge {
     Noop,
-    Record(Box<dyn GaugeWrite>),
+    Record(Arc<dyn GaugeWrite>),
+    /// A builtin atomic gauge stores the bits of a `f64`, updated without dynamic dispatch.
+    Atomic(Arc<AtomicU64>),
 }
 
 impl Gauge {
     /// Increments the gauge.
     #[inline]
     pub fn increment(&self, value: f64) {
+        if DISABLED {
+            return;
+        }
+
         match self {
             Gauge::Noop => {}
             Gauge::Record(raw_gauge) => raw_gauge.increment(value),
+            Gauge::Atomic(atomic) => fetch_update_f64(atomic, |curr| Some(curr + value)),
         }
     }
 
     /// Decrements the gauge.
     #[inline]
     pub fn decrement(&self, value: f64) {
+        if DISABLED {
+            return;
+        }
+
         match self {
             Gauge::Noop => {}
             Gauge::Record(raw_gauge) => raw_gauge.decrement(value),
+            Gauge::Atomic(atomic) => fetch_update_f64(atomic, |curr| Some(curr - value)),
         }
     }
 
     /// Set the gauge.
     #[inline]
     pub fn set(&self, value: f64) {
+        if DISABLED {
+            return;
+        }
+
         match self {
             Gauge::Noop => {}
             Gauge::Record(raw_gauge) => raw_gauge.set(value),
+            Gauge::Atomic(atomic) => atomic.store(value.to_bits(), AtomicOrdering::Relaxed),
         }
     }
+
+    /// Increments the gauge by `value`, and returns a guard which decrements it
+    /// by the same `value` when dropped, also on panic or future cancellation.
+    #[inline]
+    pub fn increment_guard(&self, value: f64) -> GaugeGuard<'_> {
+        self.increment(value);
+
+        GaugeGuard { gauge: self, value }
+    }
+}
+
+/// A guard decrements a [`Gauge`] when dropped, created by [`Gauge::increment_guard`].
+#[must_use = "The gauge is decremented immediately if unused."]
+pub struct GaugeGuard<'a> {
+    gauge: &'a Gauge,
+    value: f64,
+}
+
+impl Drop for GaugeGuard<'_> {
+    fn drop(&mut self) {
+        self.gauge.decrement(self.value);
+    }
 }
 
 /// Registry implemenation should implement this trait for `instrument histogam`.
@@ -124,33 +565,128 @@ pub trait HistogramWrite: Send + Sync {
 }
 
 /// `Histogam` measuring instrument.
+///
+/// Cloning is cheap, clones write to the same underlying series.
+#[derive(Clone)]
 pub enum Histogram {
     Noop,
-    Record(Box<dyn HistogramWrite>),
+    Record(Arc<dyn HistogramWrite>),
+    /// A builtin bucketed histogram, updated without dynamic dispatch.
+    Atomic(Arc<AtomicHistogram>),
 }
 
 impl Histogram {
     /// See [`record`](Histogram::record)
     #[inline]
     pub fn record(&self, value: f64) {
+        if DISABLED {
+            return;
+        }
+
         match self {
             Histogram::Noop => {}
             Histogram::Record(raw_histogram) => raw_histogram.record(value),
+            Histogram::Atomic(histogram) => histogram.record(value),
+        }
+    }
+
+    /// Start a timer, which records the elapsed time in seconds into this histogram
+    /// when it is dropped, also on panic or early return.
+    #[inline]
+    pub fn start_timer(&self) -> HistogramTimer<'_> {
+        HistogramTimer {
+            histogram: self,
+            start: (!DISABLED).then(Stopwatch::start),
+        }
+    }
+
+    /// Start a timer for the calls sampled by `sampler`, returns `None` for other calls.
+    #[inline]
+    pub fn start_timer_sampled(&self, sampler: &Sampler) -> Option<HistogramTimer<'_>> {
+        sampler.sample().then(|| self.start_timer())
+    }
+
+    /// Start a timer reading `clock`, which records the elapsed time in `unit` into this
+    /// histogram when it is dropped, also on panic or early return.
+    #[inline]
+    pub fn start_timer_with<'a>(
+        &'a self,
+        clock: &'a dyn Clock,
+        unit: TimeUnit,
+    ) -> HistogramTimer<'a> {
+        HistogramTimer {
+            histogram: self,
+            start: (!DISABLED).then(|| Stopwatch::start_with(clock, unit)),
         }
     }
 }
 
+/// A guard records the elapsed time into a [`Histogram`] when dropped,
+/// created by [`Histogram::start_timer`] or [`Histogram::start_timer_with`].
+#[must_use = "The timer records immediately if unused."]
+pub struct HistogramTimer<'a> {
+    histogram: &'a Histogram,
+    start: Option<Stopwatch<'a>>,
+}
+
+impl HistogramTimer<'_> {
+    /// Stop the timer, records and returns the elapsed time in the unit of this timer.
+    pub fn stop(mut self) -> f64 {
+        self.observe().unwrap_or_default()
+    }
+
+    /// Stop the timer without recording.
+    pub fn discard(mut self) {
+        self.start = None;
+    }
+
+    fn observe(&mut self) -> Option<f64> {
+        let elapsed = self.start.take()?.elapsed();
+
+        self.histogram.record(elapsed);
+
+        Some(elapsed)
+    }
+}
+
+impl Drop for HistogramTimer<'_> {
+    fn drop(&mut self) {
+        self.observe();
+    }
+}
+
 /// Registry of measuring instruments must implement this trait.
 pub trait Registry: Send + Sync + Debug {
+    /// Register/Get measuring instrument `counter`, returns an error if the registration failed.
+    fn try_counter(&self, token: Token<'_>) -> Result<Counter, Error>;
+
+    /// Register/Get measuring instrument `gauge`, returns an error if the registration failed.
+    fn try_gauge(&self, token: Token<'_>) -> Result<Gauge, Error>;
+
+    /// Register/Get measuring instrument `histogram`, returns an error if the registration failed.
+    fn try_histogram(&self, token: Token<'_>) -> Result<Histogram, Error>;
+
     /// Register/Get measuring instrument `counter`.
+    ///
+    /// Returns [`Counter::Noop`] if the registration failed.
     #[must_use = "This will cause unnecessary performance loss."]
-    fn counter(&self, token: Token<'_>) -> Counter;
+    fn counter(&self, token: Token<'_>) -> Counter {
+        self.try_counter(token).unwrap_or(Counter::Noop)
+    }
 
     /// Register/Get measuring instrument `gauge`.
+    ///
+    /// Returns [`Gauge::Noop`] if the registration failed.
     #[must_use = "This will cause unnecessary performance loss."]
-    fn gauge(&self, token: Token<'_>) -> Gauge;
+    fn gauge(&self, token: Token<'_>) -> Gauge {
+        self.try_gauge(token).unwrap_or(Gauge::Noop)
+    }
 
     /// Register/Get measuring instrument `histogam`.
+    ///
+    /// Returns [`Histogram::Noop`] if the registration failed.
     #[must_use = "This will cause unnecessary performance loss."]
-    fn histogam(&self, token: Token<'_>) -> Histogram;
+    fn histogam(&self, token: Token<'_>) -> Histogram {
+        self.try_histogram(token).unwrap_or(Histogram::Noop)
+    }
 }
@@ -0,0 +1,68 @@
+use std::{
+    cell::Cell,
+    hash::{BuildHasher, RandomState},
+};
+
+use crate::DISABLED;
+
+thread_local! {
+    static RNG: Cell<u64> = Cell::new(RandomState::new().hash_one(0u64) | 1);
+}
+
+/// Returns the next value of the thread-local `xorshift64*` generator.
+#[inline]
+fn next_u64() -> u64 {
+    RNG.with(|rng| {
+        let mut x = rng.get();
+
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+
+        rng.set(x);
+
+        x.wrapping_mul(0x2545f4914f6cdd1d)
+    })
+}
+
+/// Samples one in `period` calls, for high-frequency instrumentation.
+///
+/// Counts of sampled calls are scaled by [`weight`](Self::weight), so they stay unbiased.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sampler {
+    period: u64,
+}
+
+impl Sampler {
+    /// Create a sampler of `rate`, which must be `1 / period` for a whole number `period`,
+    /// e.g. `0.5`, `0.1` or `0.01`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` is not in `(0, 1]` or is not `1 / period`, which is a compile error
+    /// when evaluated in a `const` context.
+    pub const fn new(rate: f64) -> Self {
+        assert!(rate > 0.0 && rate <= 1.0, "sample rate must be in (0, 1].");
+
+        let period = (1.0 / rate + 0.5) as u64;
+
+        assert!(
+            (1.0 / rate - period as f64).abs() <= period as f64 * 1e-9,
+            "sample rate must be `1 / period` for a whole number `period`."
+        );
+
+        Self { period }
+    }
+
+    /// Returns true if this call is sampled.
+    #[inline]
+    pub fn sample(&self) -> bool {
+        !DISABLED && (self.period == 1 || next_u64().is_multiple_of(self.period))
+    }
+
+    /// Returns the number of calls one sampled call stands for.
+    #[inline]
+    pub fn weight(&self) -> u64 {
+        self.period
+    }
+}
@@ -0,0 +1,72 @@
+#![cfg(not(feature = "disabled"))]
+
+use std::{sync::Arc, time::Duration};
+
+use metricrs::{
+    AtomicHistogram, Clock, CoarseClock, Histogram, MockClock, TimeUnit,
+    global::set_global_registry, instrument,
+};
+
+mod common;
+
+use common::MockRegistry;
+
+static CLOCK: MockClock = MockClock::new();
+
+#[instrument(kind = Timer, name = "test.send", unit = Millis, clock = CLOCK)]
+fn send() {
+    CLOCK.advance(Duration::from_micros(5500));
+}
+
+#[instrument(kinds(Counter, Timer), name = "test.recv", unit = Micros, clock = CLOCK, outcome = true)]
+fn recv() -> Result<(), ()> {
+    CLOCK.advance(Duration::from_micros(7));
+    Err(())
+}
+
+#[test]
+fn clock() {
+    let registry = MockRegistry::default();
+
+    set_global_registry(registry.clone()).unwrap();
+
+    send();
+
+    let histogram = registry.histogram("test.send");
+    assert_eq!(histogram.count(), 1);
+    assert_eq!(histogram.sum(), 5.0);
+
+    recv().unwrap_err();
+
+    let histogram = registry.histogram("test.recv.duration{outcome=err}");
+    assert_eq!(histogram.count(), 1);
+    assert_eq!(histogram.sum(), 7.0);
+}
+
+#[test]
+fn time_unit() {
+    let clock = MockClock::new();
+    let storage = Arc::new(AtomicHistogram::new([]));
+    let histogram = Histogram::Atomic(storage.clone());
+
+    let timer = histogram.start_timer_with(&clock, TimeUnit::Nanos);
+    clock.advance(Duration::from_nanos(1500));
+    assert_eq!(timer.stop(), 1500.0);
+
+    let timer = histogram.start_timer_with(&clock, TimeUnit::Seconds);
+    clock.advance(Duration::from_millis(250));
+    assert_eq!(timer.stop(), 0.25);
+
+    assert_eq!(storage.count(), 2);
+}
+
+#[test]
+fn coarse_clock() {
+    let clock = CoarseClock::new(Duration::from_millis(1)).unwrap();
+
+    let start = clock.now();
+
+    std::thread::sleep(Duration::from_millis(20));
+
+    assert!(clock.now() > start);
+}
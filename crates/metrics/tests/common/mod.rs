@@ -0,0 +1,85 @@
+//! Helpers shared by integration tests.
+
+#![allow(dead_code)]
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use metricrs::{AtomicHistogram, Counter, Error, Gauge, Histogram, Registry, Token};
+
+/// A registry hands out builtin atomic instruments, indexed by `name{key=value,..}`,
+/// or `name` if there are no labels.
+#[derive(Debug, Default, Clone)]
+pub struct MockRegistry {
+    values: Arc<Mutex<HashMap<String, Arc<AtomicU64>>>>,
+    histograms: Arc<Mutex<HashMap<String, Arc<AtomicHistogram>>>>,
+    registered: Arc<AtomicU64>,
+}
+
+impl MockRegistry {
+    /// Returns the number of `try_*` calls.
+    pub fn registered(&self) -> u64 {
+        self.registered.load(Ordering::Relaxed)
+    }
+
+    pub fn value(&self, name: &str) -> Arc<AtomicU64> {
+        self.values
+            .lock()
+            .unwrap()
+            .entry(name.to_owned())
+            .or_default()
+            .clone()
+    }
+
+    /// Returns true if an instrument `name` is registered.
+    pub fn contains(&self, name: &str) -> bool {
+        self.values.lock().unwrap().contains_key(name)
+            || self.histograms.lock().unwrap().contains_key(name)
+    }
+
+    pub fn histogram(&self, name: &str) -> Arc<AtomicHistogram> {
+        self.histograms
+            .lock()
+            .unwrap()
+            .entry(name.to_owned())
+            .or_insert_with(|| Arc::new(AtomicHistogram::new([])))
+            .clone()
+    }
+}
+
+/// Returns the index of the instrument `token` in [`MockRegistry`].
+fn key(token: &Token<'_>) -> String {
+    if token.labels.is_empty() {
+        return token.name.to_string();
+    }
+
+    let labels = token
+        .labels
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>();
+
+    format!("{}{{{}}}", token.name, labels.join(","))
+}
+
+impl Registry for MockRegistry {
+    fn try_counter(&self, token: Token<'_>) -> Result<Counter, Error> {
+        self.registered.fetch_add(1, Ordering::Relaxed);
+        Ok(Counter::Atomic(self.value(&key(&token))))
+    }
+
+    fn try_gauge(&self, token: Token<'_>) -> Result<Gauge, Error> {
+        self.registered.fetch_add(1, Ordering::Relaxed);
+        Ok(Gauge::Atomic(self.value(&key(&token))))
+    }
+
+    fn try_histogram(&self, token: Token<'_>) -> Result<Histogram, Error> {
+        self.registered.fetch_add(1, Ordering::Relaxed);
+        Ok(Histogram::Atomic(self.histogram(&key(&token))))
+    }
+}
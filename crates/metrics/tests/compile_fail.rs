@@ -0,0 +1,5 @@
+#[test]
+fn instrument_options() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
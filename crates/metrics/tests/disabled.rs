@@ -0,0 +1,40 @@
+#![cfg(feature = "disabled")]
+
+use std::sync::{Arc, atomic::Ordering};
+
+use metricrs::{
+    AtomicHistogram, Counter, Histogram, MockClock, TimeUnit, global::set_global_registry,
+    instrument,
+};
+
+mod common;
+
+use common::MockRegistry;
+
+#[instrument(kinds(Counter, Timer, Gauge), name = "test.send")]
+fn send() -> usize {
+    1
+}
+
+#[test]
+fn disabled() {
+    let registry = MockRegistry::default();
+
+    set_global_registry(registry.clone()).unwrap();
+
+    assert_eq!(send(), 1);
+    assert!(!registry.contains("test.send.calls"));
+
+    let value = Arc::default();
+    let counter = Counter::Atomic(Arc::clone(&value));
+
+    counter.increment(1);
+    assert_eq!(value.load(Ordering::Relaxed), 0);
+
+    let storage = Arc::new(AtomicHistogram::new([]));
+    let histogram = Histogram::Atomic(storage.clone());
+
+    drop(histogram.start_timer_with(&MockClock::new(), TimeUnit::Nanos));
+    histogram.record(1.0);
+    assert_eq!(storage.count(), 0);
+}
@@ -0,0 +1,52 @@
+#![cfg(not(feature = "disabled"))]
+
+use std::{panic::catch_unwind, pin::pin, sync::atomic::Ordering, task::Poll};
+
+use futures::{FutureExt, future::pending};
+use futures_test::task::noop_context;
+use metricrs::{global::set_global_registry, instrument};
+
+mod common;
+
+use common::MockRegistry;
+
+#[instrument(kind = Gauge, name = "test.panic.in_flight")]
+#[instrument(kind = Timer, name = "test.panic.duration")]
+#[instrument(kind = Counter, name = "test.panic.calls")]
+fn panic_send() {
+    panic!("send failed");
+}
+
+#[instrument(kind = Gauge, name = "test.cancel.in_flight")]
+#[instrument(kind = Timer, name = "test.cancel.duration")]
+async fn cancel_send() {
+    pending::<()>().await
+}
+
+#[test]
+fn guard() {
+    let registry = MockRegistry::default();
+
+    set_global_registry(registry.clone()).unwrap();
+
+    let gauge = |name: &str| f64::from_bits(registry.value(name).load(Ordering::Relaxed));
+
+    catch_unwind(panic_send).unwrap_err();
+
+    assert_eq!(gauge("test.panic.in_flight"), 0.0);
+    assert_eq!(registry.histogram("test.panic.duration").count(), 1);
+    assert_eq!(
+        registry.value("test.panic.calls").load(Ordering::Relaxed),
+        1
+    );
+
+    {
+        let mut future = pin!(cancel_send());
+
+        assert_eq!(future.poll_unpin(&mut noop_context()), Poll::Pending);
+        assert_eq!(gauge("test.cancel.in_flight"), 1.0);
+    }
+
+    assert_eq!(gauge("test.cancel.in_flight"), 0.0);
+    assert_eq!(registry.histogram("test.cancel.duration").count(), 1);
+}
@@ -0,0 +1,98 @@
+#![cfg(not(feature = "disabled"))]
+
+use std::sync::atomic::Ordering;
+
+use metricrs::{global::set_global_registry, instrument};
+
+mod common;
+
+use common::MockRegistry;
+
+mod net {
+    use super::*;
+
+    pub struct Conn;
+
+    #[instrument(kind = Counter)]
+    impl Conn {
+        pub fn send(&self) -> usize {
+            1
+        }
+
+        pub async fn recv(&self) -> usize {
+            2
+        }
+
+        #[instrument(skip)]
+        pub fn close(&self) {}
+
+        #[metricrs::instrument(skip)]
+        pub fn reset(&self) {}
+
+        // attributes of other crates named `instrument` don't opt out.
+        #[tracing::instrument(skip(self), level = "debug")]
+        pub fn trace(&self) -> usize {
+            4
+        }
+
+        #[instrument(kind = Timer, name = "test.conn.flush")]
+        pub fn flush(&self) {}
+
+        pub const fn id(&self) -> usize {
+            5
+        }
+    }
+
+    #[instrument(kind = Counter)]
+    pub trait Stream {
+        fn read(&self) -> usize;
+
+        fn peek(&self) -> usize {
+            self.read()
+        }
+    }
+
+    #[instrument(kind = Counter, labels(role = "client"))]
+    impl Stream for Conn {
+        fn read(&self) -> usize {
+            3
+        }
+    }
+}
+
+use net::Stream;
+
+#[test]
+fn instrument_impl() {
+    let registry = MockRegistry::default();
+
+    set_global_registry(registry.clone()).unwrap();
+
+    let counter = |name: &str| registry.value(name).load(Ordering::Relaxed);
+
+    let conn = net::Conn;
+
+    assert_eq!(conn.send(), 1);
+    assert_eq!(futures::executor::block_on(conn.recv()), 2);
+    conn.close();
+    conn.reset();
+    assert_eq!(conn.trace(), 4);
+    conn.flush();
+    assert_eq!(conn.id(), 5);
+    assert_eq!(conn.peek(), 3);
+
+    assert_eq!(counter("impls::net::Conn::send"), 1);
+    assert_eq!(counter("impls::net::Conn::recv"), 1);
+    assert!(!registry.contains("impls::net::Conn::close"));
+    assert!(!registry.contains("impls::net::Conn::reset"));
+    assert_eq!(counter("impls::net::Conn::trace"), 1);
+    assert_eq!(registry.histogram("test.conn.flush").count(), 1);
+    assert!(!registry.contains("impls::net::Conn::id"));
+    assert_eq!(counter("impls::net::Stream::peek"), 1);
+    assert_eq!(
+        counter(
+            "<impls::net::Conn as impls::net::Stream>::read{role=client,rust_module_path=impls::net}"
+        ),
+        1
+    );
+}
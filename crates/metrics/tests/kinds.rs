@@ -0,0 +1,68 @@
+#![cfg(not(feature = "disabled"))]
+
+use std::sync::atomic::Ordering;
+
+use metricrs::{global::set_global_registry, instrument};
+
+mod common;
+
+use common::MockRegistry;
+
+#[instrument(kinds(Counter, Timer, Gauge), name = "test.send")]
+fn send() -> usize {
+    1
+}
+
+#[instrument(kinds(Counter, Timer, Gauge), name = "test.fetch", labels(shard = shard), outcome = true)]
+async fn fetch(shard: String) -> Result<String, String> {
+    Err(shard)
+}
+
+#[instrument(kinds(Timer, Gauge), name = "test.recv", outcome = true)]
+fn recv(ok: bool) -> Result<(), ()> {
+    if ok { Ok(()) } else { Err(()) }
+}
+
+#[test]
+fn kinds() {
+    let registry = MockRegistry::default();
+
+    set_global_registry(registry.clone()).unwrap();
+
+    let value = |name: &str| registry.value(name).load(Ordering::Relaxed);
+
+    assert_eq!(send(), 1);
+
+    assert_eq!(value("test.send.calls"), 1);
+    assert_eq!(registry.histogram("test.send.duration").count(), 1);
+    assert_eq!(f64::from_bits(value("test.send.in_flight")), 0.0);
+
+    assert_eq!(
+        futures::executor::block_on(fetch("a".to_owned())),
+        Err("a".to_owned())
+    );
+
+    assert_eq!(
+        value("test.fetch.calls{outcome=err,rust_module_path=kinds,shard=a}"),
+        1
+    );
+    assert_eq!(
+        registry
+            .histogram("test.fetch.duration{outcome=err,rust_module_path=kinds,shard=a}")
+            .count(),
+        1
+    );
+    assert!(registry.contains("test.fetch.in_flight{rust_module_path=kinds,shard=a}"));
+
+    recv(true).unwrap();
+    recv(false).unwrap_err();
+
+    // the in-flight gauge is not split by outcome, calls are counted by outcome instead.
+    assert_eq!(f64::from_bits(value("test.recv.in_flight")), 0.0);
+    assert_eq!(value("test.recv.calls{outcome=ok}"), 1);
+    assert_eq!(value("test.recv.calls{outcome=err}"), 1);
+    assert_eq!(
+        registry.histogram("test.recv.duration{outcome=ok}").count(),
+        1
+    );
+}
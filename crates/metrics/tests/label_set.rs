@@ -0,0 +1,124 @@
+#![cfg(not(feature = "disabled"))]
+
+use std::sync::atomic::Ordering;
+
+use metricrs::{
+    Counter, LabelSet, LabelValue, Token,
+    global::{Family, set_global_registry},
+};
+
+mod common;
+
+use common::MockRegistry;
+
+#[derive(Debug, Clone, Copy, LabelSet)]
+enum Method {
+    Get,
+    #[label(value = "POST")]
+    Post,
+}
+
+#[derive(LabelSet)]
+#[label(key = "class")]
+enum StatusClass {
+    ServerError,
+}
+
+#[derive(LabelSet)]
+struct Request<'a> {
+    method: Method,
+    #[label(key = "host")]
+    peer: &'a str,
+    tls: bool,
+}
+
+static REQUESTS: Family<Request<'static>, Counter> = Family::counter("test.requests");
+
+static METHODS: Family<Method, Counter> = Family::counter("test.methods").limit(1);
+
+#[test]
+fn encode() {
+    assert_eq!(Method::KEYS, ["method"]);
+    assert_eq!(Method::Get.label_value(), "get");
+    assert_eq!(Method::Post.label_value(), "POST");
+
+    assert_eq!(StatusClass::KEYS, ["class"]);
+    assert_eq!(StatusClass::ServerError.label_value(), "server_error");
+
+    assert_eq!(Request::KEYS, ["host", "method", "tls"]);
+
+    let request = Request {
+        method: Method::Post,
+        peer: "example.com",
+        tls: true,
+    };
+
+    assert_eq!(
+        request.label_values().as_ref(),
+        ["example.com", "POST", "true"]
+    );
+
+    assert_eq!(
+        Token::new("test.requests", request.labels()),
+        Token::new(
+            "test.requests",
+            &[("tls", "true"), ("method", "POST"), ("host", "example.com")]
+        )
+    );
+
+    assert_eq!(
+        request.token_hash("test.requests"),
+        Token::new("test.requests", request.labels()).hash
+    );
+}
+
+#[test]
+fn family() {
+    let registry = MockRegistry::default();
+
+    set_global_registry(registry.clone()).unwrap();
+
+    let counter = |name: &str| registry.value(name).load(Ordering::Relaxed);
+
+    for method in [Method::Get, Method::Get, Method::Post] {
+        let request = Request {
+            method,
+            peer: "example.com",
+            tls: false,
+        };
+
+        REQUESTS.get(&request).unwrap().increment(1);
+    }
+
+    assert_eq!(
+        counter("test.requests{host=example.com,method=get,tls=false}"),
+        2
+    );
+    assert_eq!(
+        counter("test.requests{host=example.com,method=POST,tls=false}"),
+        1
+    );
+
+    let peer = String::from("localhost");
+
+    let request = Request {
+        method: Method::Get,
+        peer: &peer,
+        tls: true,
+    };
+
+    REQUESTS.get(&request).unwrap().increment(1);
+
+    assert_eq!(
+        counter("test.requests{host=localhost,method=get,tls=true}"),
+        1
+    );
+
+    METHODS.get(&Method::Get).unwrap().increment(1);
+    METHODS.get(&Method::Post).unwrap().increment(1);
+    METHODS.get(&Method::Post).unwrap().increment(1);
+
+    assert_eq!(counter("test.methods{method=get}"), 1);
+    assert_eq!(counter("test.methods{method=other}"), 2);
+    assert!(!registry.contains("test.methods{method=POST}"));
+}
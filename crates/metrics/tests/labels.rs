@@ -0,0 +1,127 @@
+#![cfg(not(feature = "disabled"))]
+
+use std::{
+    panic::catch_unwind,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+use metricrs::{
+    Counter, Error, Gauge, Histogram, Registry, Token, global::set_global_registry, instrument,
+};
+
+mod common;
+
+use common::MockRegistry;
+
+#[instrument(kind = Counter, name = "test.fetch", labels(shard = shard_id.to_string(), method = "GET"), cardinality = 2)]
+fn fetch(shard_id: u32) {}
+
+#[instrument(kind = Timer, name = "test.send", labels(peer = peer))]
+async fn send(peer: String) -> String {
+    peer
+}
+
+#[instrument(kind = Counter, name = "test.recv", labels(peer = peer), outcome = true)]
+fn recv(peer: &str) -> Result<(), ()> {
+    if peer.is_empty() { Err(()) } else { Ok(()) }
+}
+
+/// A registry panics on the first registration.
+#[derive(Debug, Default)]
+struct PanicOnce {
+    panicked: AtomicBool,
+    registered: AtomicU64,
+}
+
+impl Registry for PanicOnce {
+    fn try_counter(&self, _: Token<'_>) -> Result<Counter, Error> {
+        if !self.panicked.swap(true, Ordering::Relaxed) {
+            panic!("registry panics");
+        }
+
+        self.registered.fetch_add(1, Ordering::Relaxed);
+
+        Ok(Counter::Noop)
+    }
+
+    fn try_gauge(&self, _: Token<'_>) -> Result<Gauge, Error> {
+        Ok(Gauge::Noop)
+    }
+
+    fn try_histogram(&self, _: Token<'_>) -> Result<Histogram, Error> {
+        Ok(Histogram::Noop)
+    }
+}
+
+static PANIC_ONCE: PanicOnce = PanicOnce {
+    panicked: AtomicBool::new(false),
+    registered: AtomicU64::new(0),
+};
+
+#[instrument(kind = Counter, name = "test.ping", labels(peer = peer), registry = PANIC_ONCE)]
+fn ping(peer: &str) {}
+
+#[test]
+fn panicking_registry() {
+    catch_unwind(|| ping("a")).unwrap_err();
+
+    // the callsite is still usable, the panicked registration is retried.
+    ping("a");
+    ping("a");
+    ping("b");
+
+    assert_eq!(PANIC_ONCE.registered.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn labels() {
+    let registry = MockRegistry::default();
+
+    set_global_registry(registry.clone()).unwrap();
+
+    let counter = |name: &str| registry.value(name).load(Ordering::Relaxed);
+
+    fetch(1);
+    fetch(1);
+    fetch(2);
+    fetch(3);
+    fetch(4);
+
+    assert_eq!(
+        counter("test.fetch{method=GET,rust_module_path=labels,shard=1}"),
+        2
+    );
+    assert_eq!(
+        counter("test.fetch{method=GET,rust_module_path=labels,shard=2}"),
+        1
+    );
+    assert_eq!(
+        counter("test.fetch{method=GET,rust_module_path=labels,shard=other}"),
+        2
+    );
+    assert!(!registry.contains("test.fetch{method=GET,rust_module_path=labels,shard=3}"));
+
+    assert_eq!(
+        futures::executor::block_on(send("a".to_owned())),
+        "a".to_owned()
+    );
+
+    assert_eq!(
+        registry
+            .histogram("test.send{peer=a,rust_module_path=labels}")
+            .count(),
+        1
+    );
+
+    recv("a").unwrap();
+    recv("").unwrap_err();
+
+    assert_eq!(
+        counter("test.recv{outcome=ok,peer=a,rust_module_path=labels}"),
+        1
+    );
+    assert_eq!(
+        counter("test.recv{outcome=err,peer=,rust_module_path=labels}"),
+        1
+    );
+}
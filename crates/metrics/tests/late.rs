@@ -0,0 +1,27 @@
+#![cfg(not(feature = "disabled"))]
+
+use std::sync::atomic::Ordering;
+
+use metricrs::{global::set_global_registry, instrument};
+
+mod common;
+
+use common::MockRegistry;
+
+#[instrument(kind = Counter, name = "test.late")]
+fn send() {}
+
+#[test]
+fn late_binding() {
+    send();
+    metricrs::counter!("test.late").increment(1);
+
+    let registry = MockRegistry::default();
+
+    set_global_registry(registry.clone()).unwrap();
+
+    send();
+    metricrs::counter!("test.late").increment(1);
+
+    assert_eq!(registry.value("test.late").load(Ordering::Relaxed), 2);
+}
@@ -0,0 +1,50 @@
+#![cfg(not(feature = "disabled"))]
+
+use std::sync::atomic::Ordering;
+
+use metricrs::global::set_global_registry;
+
+mod common;
+
+use common::MockRegistry;
+
+#[test]
+fn macros() {
+    let registry = MockRegistry::default();
+
+    set_global_registry(registry.clone()).unwrap();
+
+    let counter = |name: &str| registry.value(name).load(Ordering::Relaxed);
+
+    for _ in 0..3 {
+        metricrs::counter!("test.counter", "color" => "red").increment(1);
+        metricrs::gauge!("test.gauge").increment(1.0);
+        metricrs::histogram!("test.histogram", "name" => "pick",).record(1.0);
+    }
+
+    // literal callsites are registered only once.
+    assert_eq!(registry.registered(), 3);
+    assert_eq!(counter("test.counter{color=red}"), 3);
+    assert_eq!(f64::from_bits(counter("test.gauge")), 3.0);
+    assert_eq!(registry.histogram("test.histogram{name=pick}").count(), 3);
+
+    let color = String::from("blue");
+
+    for _ in 0..3 {
+        metricrs::counter!("test.counter", "color" => color.as_str()).increment(1);
+    }
+
+    // runtime callsites are registered once per distinct set of values.
+    assert_eq!(registry.registered(), 4);
+    assert_eq!(counter("test.counter{color=blue}"), 3);
+
+    const NAME: &str = "test.const";
+
+    for color in ["red", "blue", "red"] {
+        metricrs::counter!(NAME, "color" => color).increment(1);
+    }
+
+    assert_eq!(registry.registered(), 6);
+    assert_eq!(counter("test.const{color=red}"), 2);
+    assert_eq!(counter("test.const{color=blue}"), 1);
+}
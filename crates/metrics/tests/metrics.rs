@@ -0,0 +1,123 @@
+#![cfg(not(feature = "disabled"))]
+
+use std::sync::atomic::Ordering;
+
+use metricrs::{Counter, Gauge, Histogram, MetricDescription, MetricKind, Metrics};
+
+mod common;
+
+use common::MockRegistry;
+
+#[derive(Metrics)]
+struct ServerMetrics {
+    #[metric(help = "Total number of accepted connections.")]
+    accepted: Counter,
+    #[metric(name = "active_connections")]
+    active: Gauge,
+    #[metric(labels(method = "GET"), unit = "seconds")]
+    latency: Histogram,
+}
+
+#[derive(Metrics)]
+struct HttpMetrics {
+    #[metric(labels(status = "2xx"))]
+    requests: Counter,
+    server: ServerMetrics,
+    #[metric(name = "backend")]
+    client: ServerMetrics,
+}
+
+type Requests = Counter;
+
+#[derive(Metrics)]
+struct AliasMetrics {
+    #[metric(labels(method = "GET"))]
+    requests: Requests,
+    http: HttpMetrics,
+}
+
+#[test]
+fn register() {
+    let registry = MockRegistry::default();
+
+    let metrics = HttpMetrics::register(&registry, "http");
+
+    metrics.requests.increment(1);
+    metrics.server.accepted.increment(2);
+    metrics.server.active.set(3.0);
+    metrics.server.latency.record(0.5);
+    metrics.client.accepted.increment(4);
+
+    let value = |name: &str| registry.value(name).load(Ordering::Relaxed);
+
+    assert_eq!(value("http.requests{status=2xx}"), 1);
+    assert_eq!(value("http.server.accepted"), 2);
+    assert_eq!(f64::from_bits(value("http.server.active_connections")), 3.0);
+    assert!(registry.contains("http.server.latency{method=GET}"));
+    assert_eq!(value("http.backend.accepted"), 4);
+    assert!(!registry.contains("http.client.accepted"));
+}
+
+#[test]
+fn empty_prefix() {
+    let registry = MockRegistry::default();
+
+    let metrics = ServerMetrics::register(&registry, "");
+
+    metrics.accepted.increment(1);
+
+    assert_eq!(registry.value("accepted").load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn type_alias() {
+    let registry = MockRegistry::default();
+
+    let metrics = AliasMetrics::register(&registry, "api");
+
+    metrics.requests.increment(1);
+    metrics.http.requests.increment(2);
+
+    let value = |name: &str| registry.value(name).load(Ordering::Relaxed);
+
+    assert_eq!(value("api.requests{method=GET}"), 1);
+    assert_eq!(value("api.http.requests{status=2xx}"), 2);
+    assert_eq!(AliasMetrics::describe("api")[0].kind, MetricKind::Counter);
+}
+
+#[test]
+fn describe() {
+    let descriptions = HttpMetrics::describe("http");
+
+    let names = descriptions
+        .iter()
+        .map(|description| description.name.as_str())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        names,
+        [
+            "http.requests",
+            "http.server.accepted",
+            "http.server.active_connections",
+            "http.server.latency",
+            "http.backend.accepted",
+            "http.backend.active_connections",
+            "http.backend.latency",
+        ]
+    );
+
+    assert_eq!(
+        descriptions[1],
+        MetricDescription {
+            name: "http.server.accepted".to_owned(),
+            kind: MetricKind::Counter,
+            help: Some("Total number of accepted connections."),
+            unit: None,
+        }
+    );
+
+    assert_eq!(descriptions[2].kind, MetricKind::Gauge);
+    assert_eq!(descriptions[3].kind, MetricKind::Histogram);
+    assert_eq!(descriptions[3].unit, Some("seconds"));
+}
@@ -0,0 +1,74 @@
+#![cfg(not(feature = "disabled"))]
+
+use metricrs::{global::set_global_registry, instrument};
+
+mod common;
+
+use common::MockRegistry;
+
+mod net {
+    use super::*;
+
+    pub struct LoopFetch;
+
+    impl LoopFetch {
+        #[instrument(kind = Counter)]
+        pub fn fetch_once(&self) {}
+    }
+
+    #[instrument(kind = Timer)]
+    impl LoopFetch {
+        pub async fn fetch_async(&self) {}
+    }
+
+    pub struct Pool<T>(pub T);
+
+    #[instrument(kind = Counter)]
+    impl<T> Pool<T> {
+        pub fn get(&self) -> &T {
+            &self.0
+        }
+    }
+
+    #[instrument(kind = Gauge)]
+    pub fn connect() {}
+
+    pub struct Client;
+
+    impl Client {
+        #[instrument(kind = Counter)]
+        pub fn send(&self) {}
+    }
+
+    pub struct Server;
+
+    impl Server {
+        #[instrument(kind = Timer)]
+        pub fn send(&self) {}
+    }
+}
+
+#[test]
+fn default_name() {
+    let registry = MockRegistry::default();
+
+    set_global_registry(registry.clone()).unwrap();
+
+    net::LoopFetch.fetch_once();
+    net::connect();
+    futures::executor::block_on(net::LoopFetch.fetch_async());
+    net::Pool(1).get();
+
+    assert!(registry.contains("name::net::LoopFetch::fetch_once"));
+    assert!(registry.contains("name::net::LoopFetch::fetch_async"));
+    assert!(registry.contains("name::net::connect"));
+    assert!(registry.contains("name::net::Pool<_>::get"));
+
+    // methods of the same name are told apart by their self type.
+    net::Client.send();
+    net::Server.send();
+
+    assert!(registry.contains("name::net::Client::send"));
+    assert_eq!(registry.histogram("name::net::Server::send").count(), 1);
+    assert!(!registry.contains("name::net::send"));
+}
@@ -0,0 +1,191 @@
+#![cfg(not(feature = "disabled"))]
+
+use std::{panic::catch_unwind, sync::atomic::Ordering};
+
+use metricrs::{global::set_global_registry, instrument};
+
+mod common;
+
+use common::MockRegistry;
+
+#[instrument(kind = Counter, name = "test.fetch.calls", outcome = true)]
+fn fetch(ok: bool) -> Result<usize, String> {
+    if !ok {
+        return Err("fetch failed".to_owned());
+    }
+
+    Ok(1)
+}
+
+#[instrument(kind = Timer, name = "test.fetch.duration", outcome = true)]
+async fn fetch_async(ok: bool) -> Result<usize, String> {
+    let value = fetch(ok)?;
+
+    Ok(value)
+}
+
+#[instrument(kind = Counter, name = "test.fetch_panic.calls", outcome = true)]
+fn fetch_panic() -> Result<(), String> {
+    panic!("fetch panics");
+}
+
+#[instrument(kind = Gauge, name = "test.connect", outcome = true)]
+fn connect(ok: bool) -> Result<(), String> {
+    if ok {
+        Ok(())
+    } else {
+        Err("refused".to_owned())
+    }
+}
+
+#[instrument(name = "test.bytes", outcome = true)]
+fn bytes(ok: bool) -> Result<impl Iterator<Item = u8>, String> {
+    if ok {
+        Ok([1, 2].into_iter())
+    } else {
+        Err("no bytes".to_owned())
+    }
+}
+
+#[instrument(name = "test.find", outcome = true)]
+fn find(values: &[&str]) -> Result<u32, String> {
+    // `return` in a closure returns from the closure only.
+    let parse = |value: &str| -> Result<u32, String> {
+        if value.is_empty() {
+            return Err("empty".to_owned());
+        }
+
+        value.parse().map_err(|_| value.to_owned())
+    };
+
+    for value in values {
+        if let Ok(value) = parse(value) {
+            return Ok(value);
+        }
+    }
+
+    Err("not found".to_owned())
+}
+
+#[instrument(name = "test.retry", outcome = true)]
+fn retry() -> Result<u32, String> {
+    let mut attempts = 0;
+
+    loop {
+        attempts += 1;
+
+        if attempts == 3 {
+            return Ok(attempts);
+        }
+    }
+}
+
+macro_rules! return_ok {
+    ($value:expr) => {
+        return Ok($value)
+    };
+}
+
+#[instrument(name = "test.cached", outcome = true)]
+fn cached(hit: bool) -> Result<u32, String> {
+    if hit {
+        // `return` expanded from a macro is recorded like one in the body.
+        return_ok!(1);
+    }
+
+    Err("miss".to_owned())
+}
+
+struct Fetcher(String);
+
+impl Fetcher {
+    #[instrument(name = "test.fetcher.get", outcome = true)]
+    fn get(&self) -> Result<&str, String> {
+        Ok(&self.0)
+    }
+
+    #[instrument(name = "test.fetcher.get_mut", outcome = true)]
+    fn get_mut(&mut self) -> Result<&mut String, String> {
+        Ok(&mut self.0)
+    }
+
+    #[instrument(name = "test.fetcher.into_inner", outcome = true)]
+    fn into_inner(self) -> Result<String, String> {
+        Ok(self.0)
+    }
+}
+
+#[test]
+fn outcome() {
+    let registry = MockRegistry::default();
+
+    set_global_registry(registry.clone()).unwrap();
+
+    let counter = |name: &str| registry.value(name).load(Ordering::Relaxed);
+
+    assert_eq!(fetch(true), Ok(1));
+    assert!(fetch(false).is_err());
+    assert!(fetch(false).is_err());
+
+    assert_eq!(counter("test.fetch.calls{outcome=ok}"), 1);
+    assert_eq!(counter("test.fetch.calls{outcome=err}"), 2);
+
+    assert_eq!(futures::executor::block_on(fetch_async(true)), Ok(1));
+    assert!(futures::executor::block_on(fetch_async(false)).is_err());
+
+    assert_eq!(
+        registry
+            .histogram("test.fetch.duration{outcome=ok}")
+            .count(),
+        1
+    );
+    assert_eq!(
+        registry
+            .histogram("test.fetch.duration{outcome=err}")
+            .count(),
+        1
+    );
+
+    let mut fetcher = Fetcher("fetched".to_owned());
+
+    fetcher.get_mut().unwrap().push('!');
+
+    assert_eq!(fetcher.get(), Ok("fetched!"));
+    assert_eq!(fetcher.into_inner(), Ok("fetched!".to_owned()));
+
+    assert_eq!(counter("test.fetcher.get{outcome=ok}"), 1);
+    assert_eq!(counter("test.fetcher.into_inner{outcome=ok}"), 1);
+
+    assert!(connect(true).is_ok());
+    assert!(connect(false).is_err());
+
+    assert_eq!(f64::from_bits(counter("test.connect")), 0.0);
+    assert_eq!(counter("test.connect.calls{outcome=ok}"), 1);
+    assert_eq!(counter("test.connect.calls{outcome=err}"), 1);
+
+    assert_eq!(bytes(true).unwrap().collect::<Vec<_>>(), [1, 2]);
+    assert!(bytes(false).is_err());
+
+    assert_eq!(counter("test.bytes{outcome=ok}"), 1);
+    assert_eq!(counter("test.bytes{outcome=err}"), 1);
+
+    assert_eq!(find(&["", "x", "2"]), Ok(2));
+    assert!(find(&[""]).is_err());
+
+    assert_eq!(counter("test.find{outcome=ok}"), 1);
+    assert_eq!(counter("test.find{outcome=err}"), 1);
+
+    assert_eq!(retry(), Ok(3));
+
+    assert_eq!(counter("test.retry{outcome=ok}"), 1);
+
+    assert_eq!(cached(true), Ok(1));
+
+    assert_eq!(counter("test.cached{outcome=ok}"), 1);
+    assert_eq!(counter("test.cached{outcome=err}"), 0);
+
+    catch_unwind(fetch_panic).unwrap_err();
+
+    assert_eq!(counter("test.fetch_panic.calls{outcome=ok}"), 0);
+    assert_eq!(counter("test.fetch_panic.calls{outcome=err}"), 1);
+}
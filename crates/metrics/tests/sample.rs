@@ -0,0 +1,119 @@
+#![cfg(not(feature = "disabled"))]
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use metricrs::{
+    AtomicHistogram, Counter, Histogram, Sampler, global::set_global_registry, instrument,
+};
+
+mod common;
+
+use common::MockRegistry;
+
+#[instrument(kinds(Counter, Timer), name = "test.send", sample = 0.25)]
+fn send() {}
+
+#[instrument(kind = Counter, name = "test.recv", sample = 1)]
+fn recv() {}
+
+#[instrument(kinds(Counter, Gauge), name = "test.busy", sample = 0.5)]
+fn busy(registry: &MockRegistry) -> f64 {
+    f64::from_bits(
+        registry
+            .value("test.busy.in_flight")
+            .load(Ordering::Relaxed),
+    )
+}
+
+fn peer(lookups: &AtomicU64) -> &'static str {
+    lookups.fetch_add(1, Ordering::Relaxed);
+
+    "example.com"
+}
+
+#[instrument(kind = Counter, name = "test.fetch", labels(peer = peer(lookups)), sample = 0.25)]
+fn fetch(lookups: &AtomicU64) {}
+
+#[instrument(kinds(Counter, Gauge), name = "test.poll", labels(peer = peer(lookups)), sample = 0.25)]
+fn poll(lookups: &AtomicU64) {}
+
+#[test]
+fn sample() {
+    let registry = MockRegistry::default();
+
+    set_global_registry(registry.clone()).unwrap();
+
+    for _ in 0..1000 {
+        send();
+        recv();
+    }
+
+    let calls = registry.value("test.send.calls").load(Ordering::Relaxed);
+    let timed = registry.histogram("test.send.duration").count();
+
+    // every sampled call counts for 4 calls.
+    assert_eq!(calls, timed * 4);
+    assert!(timed > 0 && timed < 1000);
+
+    assert_eq!(registry.value("test.recv").load(Ordering::Relaxed), 1000);
+
+    // in-flight gauges are not sampled.
+    for _ in 0..100 {
+        assert_eq!(busy(&registry), 1.0);
+    }
+
+    // label values are only evaluated for sampled calls.
+    let lookups = AtomicU64::new(0);
+
+    for _ in 0..1000 {
+        fetch(&lookups);
+    }
+
+    let calls = registry
+        .value("test.fetch{peer=example.com,rust_module_path=sample}")
+        .load(Ordering::Relaxed);
+
+    assert_eq!(calls, lookups.load(Ordering::Relaxed) * 4);
+    assert!(calls > 0 && calls < 4000);
+
+    // unless a gauge needs them.
+    let lookups = AtomicU64::new(0);
+
+    for _ in 0..100 {
+        poll(&lookups);
+    }
+
+    assert_eq!(lookups.load(Ordering::Relaxed), 100);
+}
+
+#[test]
+fn sampler() {
+    let sampler = Sampler::new(0.01);
+
+    assert_eq!(sampler.weight(), 100);
+    assert!(Sampler::new(1.0).sample());
+
+    let value = Arc::default();
+    let counter = Counter::Atomic(Arc::clone(&value));
+
+    for _ in 0..100 {
+        counter.increment_sampled(1, &Sampler::new(0.5));
+    }
+
+    assert_eq!(value.load(Ordering::Relaxed) % 2, 0);
+
+    let storage = Arc::new(AtomicHistogram::new([]));
+    let histogram = Histogram::Atomic(storage.clone());
+
+    assert!(histogram.start_timer_sampled(&Sampler::new(1.0)).is_some());
+    assert_eq!(storage.count(), 1);
+}
+
+#[test]
+#[should_panic(expected = "sample rate must be `1 / period`")]
+fn sampler_rate() {
+    let _ = Sampler::new(0.3);
+}
@@ -0,0 +1,49 @@
+#![cfg(not(feature = "disabled"))]
+
+use std::sync::{LazyLock, OnceLock, atomic::Ordering};
+
+use metricrs::{global::get_global_registry, instrument};
+
+mod common;
+
+use common::MockRegistry;
+
+static LAZY_REGISTRY: LazyLock<MockRegistry> = LazyLock::new(MockRegistry::default);
+
+static ONCE_REGISTRY: OnceLock<MockRegistry> = OnceLock::new();
+
+#[instrument(kind = Counter, name = "test.send", registry = LAZY_REGISTRY)]
+fn send() {}
+
+#[instrument(kinds(Counter, Timer), name = "test.recv", labels(peer = peer), registry = ONCE_REGISTRY)]
+fn recv(peer: &str) {}
+
+#[test]
+fn registry_target() {
+    send();
+    send();
+
+    assert_eq!(LAZY_REGISTRY.value("test.send").load(Ordering::Relaxed), 2);
+
+    // not measured until the registry is initialized.
+    recv("a");
+
+    let registry = ONCE_REGISTRY.get_or_init(MockRegistry::default);
+
+    recv("a");
+
+    assert_eq!(
+        registry
+            .value("test.recv.calls{peer=a,rust_module_path=target}")
+            .load(Ordering::Relaxed),
+        1
+    );
+    assert_eq!(
+        registry
+            .histogram("test.recv.duration{peer=a,rust_module_path=target}")
+            .count(),
+        1
+    );
+
+    assert!(get_global_registry().is_none());
+}
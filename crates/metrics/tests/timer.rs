@@ -0,0 +1,32 @@
+#![cfg(not(feature = "disabled"))]
+
+use std::{
+    panic::{AssertUnwindSafe, catch_unwind},
+    sync::Arc,
+};
+
+use metricrs::{AtomicHistogram, Histogram};
+
+#[test]
+fn timer() {
+    let storage = Arc::new(AtomicHistogram::new([1.0]));
+    let histogram = Histogram::Atomic(storage.clone());
+
+    drop(histogram.start_timer());
+    assert_eq!(storage.count(), 1);
+
+    let elapsed = histogram.start_timer().stop();
+    assert_eq!(storage.count(), 2);
+    assert!(storage.sum() >= elapsed);
+
+    histogram.start_timer().discard();
+    assert_eq!(storage.count(), 2);
+
+    catch_unwind(AssertUnwindSafe(|| {
+        let _timer = histogram.start_timer();
+        panic!("timed section panics");
+    }))
+    .unwrap_err();
+
+    assert_eq!(storage.count(), 3);
+}
@@ -0,0 +1,67 @@
+use std::borrow::Cow;
+
+use metricrs::{Labels, Token, TokenHasher, canonical_labels};
+
+#[test]
+fn owned_labels() {
+    let method = String::from("GET");
+
+    let borrowed = Token::new("http.requests", &[("method", "GET")]);
+    let owned = Token::new(
+        String::from("http.requests"),
+        vec![("method", method.clone())],
+    );
+
+    assert_eq!(borrowed.hash, owned.hash);
+    assert_eq!(borrowed, owned);
+    assert_eq!(borrowed.clone().into_owned(), owned);
+
+    assert!(matches!(owned.labels, Labels::Owned(_)));
+    assert_eq!(
+        owned.labels.iter().collect::<Vec<_>>(),
+        [("method", method.as_str())]
+    );
+
+    let other = Token::new(
+        Cow::Borrowed("http.requests"),
+        vec![("method", String::from("POST"))],
+    );
+
+    assert_ne!(borrowed.hash, other.hash);
+}
+
+#[test]
+fn stable_hash() {
+    const TOKEN: Token<'static> = Token::borrowed("http.requests", &[("method", "GET")]);
+
+    assert_eq!(TOKEN.hash, 0x51188e895be217fb);
+    assert_eq!(TOKEN, Token::new("http.requests", &[("method", "GET")]));
+
+    let mut hasher = TokenHasher::new();
+    hasher.write(b"");
+    assert_eq!(hasher.finish(), 0xcbf29ce484222325);
+    hasher.write(b"a");
+    assert_eq!(hasher.finish(), 0xaf63dc4c8601ec8c);
+}
+
+#[test]
+fn canonical_order() {
+    const LABELS: &[(&str, &str)] = &canonical_labels([("b", "2"), ("a", "1")]);
+    const TOKEN: Token<'static> = Token::borrowed("test", LABELS);
+
+    let token = Token::new("test", &[("b", "2"), ("a", "1")]);
+
+    assert_eq!(token, Token::new("test", &[("a", "1"), ("b", "2")]));
+    assert_eq!(token, TOKEN);
+    assert_eq!(
+        token.labels.iter().collect::<Vec<_>>(),
+        [("a", "1"), ("b", "2")]
+    );
+
+    let token = Token::new("test", vec![("a", "1"), ("b", "2"), ("a", "3")]);
+
+    assert_eq!(
+        token.labels.iter().collect::<Vec<_>>(),
+        [("a", "3"), ("b", "2")]
+    );
+}
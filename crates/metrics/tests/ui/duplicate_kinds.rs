@@ -0,0 +1,8 @@
+use metricrs::instrument;
+
+#[instrument(kinds(Counter, Timer, Counter))]
+fn send() {}
+
+fn main() {
+    send();
+}
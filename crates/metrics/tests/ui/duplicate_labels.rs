@@ -0,0 +1,8 @@
+use metricrs::instrument;
+
+#[instrument(labels(peer = "a"), labels(peer = "b"))]
+fn send() {}
+
+fn main() {
+    send();
+}
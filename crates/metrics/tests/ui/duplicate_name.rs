@@ -0,0 +1,8 @@
+use metricrs::instrument;
+
+#[instrument(name = "net.send", name = "net.recv")]
+fn send() {}
+
+fn main() {
+    send();
+}
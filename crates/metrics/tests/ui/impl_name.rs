@@ -0,0 +1,12 @@
+use metricrs::instrument;
+
+struct Conn;
+
+#[instrument(kind = Counter, name = "conn")]
+impl Conn {
+    fn send(&self) {}
+}
+
+fn main() {
+    Conn.send();
+}
@@ -0,0 +1,8 @@
+use metricrs::instrument;
+
+#[instrument(kind = Counter, kinds(Timer))]
+fn send() {}
+
+fn main() {
+    send();
+}
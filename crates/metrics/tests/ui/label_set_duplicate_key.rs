@@ -0,0 +1,10 @@
+use metricrs::LabelSet;
+
+#[derive(LabelSet)]
+struct Request {
+    peer: String,
+    #[label(key = "peer")]
+    host: String,
+}
+
+fn main() {}
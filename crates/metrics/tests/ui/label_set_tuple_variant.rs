@@ -0,0 +1,9 @@
+use metricrs::LabelSet;
+
+#[derive(LabelSet)]
+enum Method {
+    Get,
+    Other(String),
+}
+
+fn main() {}
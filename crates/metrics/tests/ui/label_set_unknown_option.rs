@@ -0,0 +1,9 @@
+use metricrs::LabelSet;
+
+#[derive(LabelSet)]
+struct Request {
+    #[label(value = "peer")]
+    host: String,
+}
+
+fn main() {}
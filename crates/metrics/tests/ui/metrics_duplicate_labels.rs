@@ -0,0 +1,9 @@
+use metricrs::{Counter, Metrics};
+
+#[derive(Metrics)]
+struct Requests {
+    #[metric(labels(method = "GET"), labels(status = "200"))]
+    total: Counter,
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use metricrs::{Counter, Metrics};
+
+#[derive(Metrics)]
+struct Requests {
+    total: Counter,
+}
+
+#[derive(Metrics)]
+struct Server {
+    #[metric(labels(method = "GET"))]
+    requests: Requests,
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use metricrs::{Counter, Metrics};
+
+#[derive(Metrics)]
+struct Requests(Counter);
+
+fn main() {}
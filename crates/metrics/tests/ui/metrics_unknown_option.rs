@@ -0,0 +1,9 @@
+use metricrs::{Counter, Metrics};
+
+#[derive(Metrics)]
+struct Requests {
+    #[metric(description = "total requests")]
+    total: Counter,
+}
+
+fn main() {}
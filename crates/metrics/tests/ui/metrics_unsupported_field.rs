@@ -0,0 +1,8 @@
+use metricrs::{Counter, Metrics};
+
+#[derive(Metrics)]
+struct Requests {
+    total: Option<Counter>,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use metricrs::instrument;
+
+#[instrument(labels(peer = 1))]
+fn send() {}
+
+fn main() {
+    send();
+}
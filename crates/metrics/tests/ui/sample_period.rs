@@ -0,0 +1,8 @@
+use metricrs::instrument;
+
+#[instrument(kind = Counter, sample = 0.3)]
+fn send() {}
+
+fn main() {
+    send();
+}
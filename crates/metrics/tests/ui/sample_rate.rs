@@ -0,0 +1,8 @@
+use metricrs::instrument;
+
+#[instrument(kind = Counter, sample = 1.5)]
+fn send() {}
+
+fn main() {
+    send();
+}
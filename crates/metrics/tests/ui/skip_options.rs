@@ -0,0 +1,8 @@
+use metricrs::instrument;
+
+#[instrument(skip, kind = Counter)]
+fn send() {}
+
+fn main() {
+    send();
+}
@@ -0,0 +1,8 @@
+use metricrs::instrument;
+
+#[instrument(labels(peer = "a"), cardinality = 16)]
+fn send() {}
+
+fn main() {
+    send();
+}
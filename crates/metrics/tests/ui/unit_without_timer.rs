@@ -0,0 +1,8 @@
+use metricrs::instrument;
+
+#[instrument(kind = Counter, unit = Millis)]
+fn send() {}
+
+fn main() {
+    send();
+}
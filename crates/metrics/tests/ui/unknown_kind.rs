@@ -0,0 +1,8 @@
+use metricrs::instrument;
+
+#[instrument(kind = Timmer)]
+fn send() {}
+
+fn main() {
+    send();
+}
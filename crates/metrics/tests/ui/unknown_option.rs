@@ -0,0 +1,8 @@
+use metricrs::instrument;
+
+#[instrument(kind = Counter, level = "debug")]
+fn send() {}
+
+fn main() {
+    send();
+}
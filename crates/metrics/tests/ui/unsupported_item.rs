@@ -0,0 +1,6 @@
+use metricrs::instrument;
+
+#[instrument(kind = Counter)]
+struct Conn;
+
+fn main() {}
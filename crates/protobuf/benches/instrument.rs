@@ -1,8 +1,16 @@
-use std::time::Instant;
+use std::{
+    sync::{
+        Arc, LazyLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
 use divan::bench;
 
-use metricrs::{global::set_global_registry, instrument};
+use metricrs::{
+    Clock, CoarseClock, Counter, CounterWrite, global::set_global_registry, instrument,
+};
 use metricrs_protobuf::registry::ProtoBufRegistry;
 
 fn main() {
@@ -33,11 +41,41 @@ fn mock_timer() -> usize {
     1
 }
 
+struct DynCounter(Arc<AtomicU64>);
+
+impl CounterWrite for DynCounter {
+    fn increment(&self, step: u64) {
+        self.0.fetch_add(step, Ordering::Relaxed);
+    }
+
+    fn absolute(&self, value: u64) {
+        self.0.store(value, Ordering::Relaxed);
+    }
+}
+
+static COARSE_CLOCK: LazyLock<CoarseClock> =
+    LazyLock::new(|| CoarseClock::new(Duration::from_millis(1)).unwrap());
+
+static ATOMIC_COUNTER: LazyLock<Counter> = LazyLock::new(|| Counter::Atomic(Default::default()));
+
+static DYN_COUNTER: LazyLock<Counter> =
+    LazyLock::new(|| Counter::Record(Arc::new(DynCounter(Default::default()))));
+
 #[bench(threads = 0, sample_count = 10000)]
 fn bench_counter() {
     mock_counter();
 }
 
+#[bench(threads = 0, sample_count = 10000)]
+fn bench_counter_atomic() {
+    divan::black_box(&*ATOMIC_COUNTER).increment(1);
+}
+
+#[bench(threads = 0, sample_count = 10000)]
+fn bench_counter_dyn() {
+    divan::black_box(&*DYN_COUNTER).increment(1);
+}
+
 #[bench(threads = 0, sample_count = 10000)]
 fn bench_timer() {
     mock_timer();
@@ -47,3 +85,8 @@ fn bench_timer() {
 fn bench_instant_now() {
     _ = Instant::now().elapsed();
 }
+
+#[bench(threads = 0, sample_count = 10000)]
+fn bench_coarse_clock() {
+    _ = divan::black_box(&*COARSE_CLOCK).now();
+}
@@ -16,7 +16,7 @@ impl LoopFetch {
         }
     }
 
-    #[instrument(kind = Counter)]
+    #[instrument(kind = Counter, outcome = true)]
     fn fetch_once(&mut self) -> Result<()> {
         let query_result = self.fetch.query(Query {
             version: self.verson,
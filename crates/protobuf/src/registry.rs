@@ -1,9 +1,9 @@
 //! A poll-style and in-memory metrics collector implementation.
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, hash_map::Entry},
     fmt::Debug,
-    io::{Read, Result, Write as _},
+    io::{Error, ErrorKind, Read, Result, Write as _},
     net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
     sync::{
         Arc,
@@ -15,60 +15,28 @@ use parking_lot::RwLock;
 use protobuf::Message;
 
 use metricrs::{
-    Counter, CounterWrite, Gauge, GaugeWrite, Histogram, HistogramWrite, Registry, Token,
+    AtomicHistogram, Counter, Error as RegistryError, Gauge, Histogram, Registry, Token,
 };
 
-use crate::protos::memory::{Instrument, Label, Metadata, Query, QueryResult, Value};
-
-struct Write(Arc<AtomicU64>);
-
-impl Write {
-    #[inline]
-    fn new(value: Arc<AtomicU64>) -> Self {
-        Self(value)
-    }
-}
-
-#[allow(unused)]
-impl CounterWrite for Write {
-    fn increment(&self, step: u64) {
-        self.0.fetch_add(step, Ordering::AcqRel);
-    }
-
-    fn absolute(&self, value: u64) {
-        self.0.swap(value, Ordering::AcqRel);
-    }
-}
-
-#[allow(unused)]
-impl HistogramWrite for Write {
-    fn record(&self, value: f64) {
-        self.0.swap(value.to_bits(), Ordering::AcqRel);
-    }
-}
-
-#[allow(unused)]
-impl GaugeWrite for Write {
-    fn increment(&self, value: f64) {
-        self.0
-            .fetch_update(Ordering::AcqRel, Ordering::Relaxed, |curr| {
-                let input = f64::from_bits(curr);
-                let output = input + value;
-                Some(output.to_bits())
-            });
-    }
-
-    fn decrement(&self, value: f64) {
-        self.0
-            .fetch_update(Ordering::AcqRel, Ordering::Relaxed, |curr| {
-                let input = f64::from_bits(curr);
-                let output = input - value;
-                Some(output.to_bits())
-            });
-    }
+use crate::protos::memory::{
+    HistogramValue, Instrument, Label, Metadata, Query, QueryResult, Value,
+};
 
-    fn set(&self, value: f64) {
-        self.0.swap(value.to_bits(), Ordering::AcqRel);
+/// Default histogram bucket upper bounds, tuned for latencies in seconds.
+pub const DEFAULT_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+fn histogram_value(hash: u64, histogram: &AtomicHistogram) -> HistogramValue {
+    HistogramValue {
+        hash,
+        bounds: histogram.bounds().to_vec(),
+        buckets: histogram.buckets().collect(),
+        count: histogram.count(),
+        sum: histogram.sum(),
+        min: histogram.min().unwrap_or_default(),
+        max: histogram.max().unwrap_or_default(),
+        ..Default::default()
     }
 }
 
@@ -77,7 +45,7 @@ impl<'a> From<(Instrument, Token<'a>)> for Metadata {
         Self {
             hash: value.hash,
             instrument: instrument.into(),
-            name: value.name.to_owned(),
+            name: value.name.into_owned(),
             labels: value
                 .labels
                 .iter()
@@ -92,11 +60,55 @@ impl<'a> From<(Instrument, Token<'a>)> for Metadata {
     }
 }
 
-#[derive(Default)]
-struct MutableData {
-    instruments: HashMap<u64, Arc<AtomicU64>>,
-    metadata: HashMap<u64, Metadata>,
-    version: u64,
+/// Number of [`Shard`]s, must be a power of two.
+const SHARDS: usize = 32;
+
+/// Storage of one registered instrument.
+#[derive(Clone)]
+enum Slot {
+    Value(Arc<AtomicU64>),
+    Histogram(Arc<AtomicHistogram>),
+}
+
+impl Slot {
+    /// Returns true if no instrument handle refers to this slot anymore.
+    fn is_dropped(&self) -> bool {
+        match self {
+            Slot::Value(value) => Arc::strong_count(value) == 1,
+            Slot::Histogram(histogram) => Arc::strong_count(histogram) == 1,
+        }
+    }
+}
+
+struct Series {
+    metadata: Metadata,
+    slot: Slot,
+}
+
+/// Registered instruments, indexed by [`Token::hash`].
+type Shard = HashMap<u64, Series>;
+
+/// Sharded storage of registered instruments.
+struct Storage {
+    shards: Box<[RwLock<Shard>]>,
+    /// Metadata version, increased when a new instrument is registered.
+    version: AtomicU64,
+}
+
+impl Default for Storage {
+    fn default() -> Self {
+        Self {
+            shards: (0..SHARDS).map(|_| Default::default()).collect(),
+            version: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Storage {
+    #[inline]
+    fn shard(&self, hash: u64) -> &RwLock<Shard> {
+        &self.shards[hash as usize & (SHARDS - 1)]
+    }
 }
 
 /// A builtin in-memory [`Registry`](crate::Registry) implementation works
@@ -104,7 +116,8 @@ struct MutableData {
 #[derive(Clone)]
 pub struct ProtoBufRegistry {
     local_addr: SocketAddr,
-    mutable: Arc<RwLock<MutableData>>,
+    bounds: Arc<[f64]>,
+    storage: Arc<Storage>,
 }
 
 impl Debug for ProtoBufRegistry {
@@ -122,15 +135,37 @@ impl ProtoBufRegistry {
     }
 
     /// Create `MemoryRegistry` and start a `TCP` server to accept remote `status` queries.
+    ///
+    /// Histograms use the [`DEFAULT_BUCKETS`] boundaries.
     pub fn bind<S>(laddr: S) -> Result<Self>
     where
         S: ToSocketAddrs,
     {
+        Self::bind_with_buckets(laddr, DEFAULT_BUCKETS)
+    }
+
+    /// Like [`bind`](Self::bind), but histograms use the custom bucket upper `bounds`.
+    ///
+    /// `bounds` must be finite and strictly increasing.
+    pub fn bind_with_buckets<S>(laddr: S, bounds: &[f64]) -> Result<Self>
+    where
+        S: ToSocketAddrs,
+    {
+        if bounds.iter().any(|bound| !bound.is_finite())
+            || bounds.windows(2).any(|pair| pair[0] >= pair[1])
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "histogram bucket bounds must be finite and strictly increasing",
+            ));
+        }
+
         let listener = TcpListener::bind(laddr)?;
 
         let registry = ProtoBufRegistry {
             local_addr: listener.local_addr()?,
-            mutable: Default::default(),
+            bounds: bounds.into(),
+            storage: Default::default(),
         };
 
         let server = registry.clone();
@@ -176,42 +211,50 @@ impl ProtoBufRegistry {
 
         let query = Query::parse_from_bytes(&buf)?;
 
-        let mutable = self.mutable.read();
+        // load `version` before scanning shards, instruments registered during the scan
+        // increase it, and their metadata will be sent again with the next query.
+        let version = self.storage.version.load(Ordering::Acquire);
 
         let mut metadatas = vec![];
         let mut values = vec![];
+        let mut histograms = vec![];
 
-        let version = mutable.version;
-
-        if version > query.version {
-            for metadata in mutable.metadata.values().clone() {
-                metadatas.push(metadata.clone());
-            }
-        }
+        for shard in self.storage.shards.iter() {
+            let mut removed = vec![];
 
-        let mut removed = vec![];
+            for (hash, series) in shard.read().iter() {
+                if version > query.version {
+                    metadatas.push(series.metadata.clone());
+                }
 
-        for (hash, value) in mutable.instruments.iter() {
-            values.push(Value {
-                hash: *hash,
-                value: value.load(Ordering::Relaxed),
-                ..Default::default()
-            });
+                match &series.slot {
+                    Slot::Value(value) => values.push(Value {
+                        hash: *hash,
+                        value: value.load(Ordering::Relaxed),
+                        ..Default::default()
+                    }),
+                    Slot::Histogram(histogram) => {
+                        histograms.push(histogram_value(*hash, histogram))
+                    }
+                }
 
-            if Arc::strong_count(value) == 1 {
-                removed.push(*hash);
-                continue;
+                if series.slot.is_dropped() {
+                    removed.push(*hash);
+                }
             }
-        }
-
-        drop(mutable);
 
-        if !removed.is_empty() {
-            let mut mutable = self.mutable.write();
+            if !removed.is_empty() {
+                let mut shard = shard.write();
 
-            for removed in removed {
-                assert!(mutable.metadata.remove(&removed).is_some());
-                assert!(mutable.instruments.remove(&removed).is_some());
+                for removed in removed {
+                    // re-check, the instrument may be registered again after the read lock is released.
+                    if shard
+                        .get(&removed)
+                        .is_some_and(|series| series.slot.is_dropped())
+                    {
+                        shard.remove(&removed);
+                    }
+                }
             }
         }
 
@@ -219,6 +262,7 @@ impl ProtoBufRegistry {
             values,
             metadatas,
             version,
+            histograms,
             ..Default::default()
         };
 
@@ -232,37 +276,112 @@ impl ProtoBufRegistry {
         Ok(())
     }
 
-    fn get(&self, instrument: Instrument, token: Token<'_>) -> Arc<AtomicU64> {
-        if let Some(counter) = self.mutable.read().instruments.get(&token.hash) {
-            return counter.clone();
+    /// Check whether the registered `metadata` describes the same instrument as `token`.
+    ///
+    /// Returns an error, if the `hash` collides with another `name`/`labels` or the instrument
+    /// is already registered as another kind.
+    fn check(
+        metadata: &Metadata,
+        instrument: Instrument,
+        token: &Token<'_>,
+    ) -> std::result::Result<(), RegistryError> {
+        if metadata.name != token.name
+            || !metadata
+                .labels
+                .iter()
+                .map(|label| (label.key.as_str(), label.value.as_str()))
+                .eq(token.labels.iter())
+        {
+            log::error!(
+                "instrument `{}` hash collides with `{}`, hash={:#x}",
+                token.name,
+                metadata.name,
+                token.hash
+            );
+
+            return Err(RegistryError::HashCollision {
+                name: token.name.to_string(),
+                other: metadata.name.clone(),
+            });
+        }
+
+        if metadata.instrument.enum_value_or_default() != instrument {
+            log::error!(
+                "instrument `{}` is already registered as {:?}, not {:?}",
+                token.name,
+                metadata.instrument.enum_value_or_default(),
+                instrument
+            );
+
+            return Err(RegistryError::KindConflict(token.name.to_string()));
         }
 
-        let value: Arc<AtomicU64> = Default::default();
+        Ok(())
+    }
 
-        let mut mutable_data = self.mutable.write();
+    /// Get or register the instrument `token`, `slot` creates the storage of a new instrument.
+    fn get(
+        &self,
+        instrument: Instrument,
+        token: Token<'_>,
+        slot: impl FnOnce() -> Slot,
+    ) -> std::result::Result<Slot, RegistryError> {
+        let shard = self.storage.shard(token.hash);
 
-        mutable_data
-            .metadata
-            .insert(token.hash, Metadata::from((instrument, token)));
+        if let Some(series) = shard.read().get(&token.hash) {
+            Self::check(&series.metadata, instrument, &token)?;
+
+            return Ok(series.slot.clone());
+        }
 
-        mutable_data.instruments.insert(token.hash, value.clone());
+        // re-check with the write lock held, another thread may have registered the same token.
+        match shard.write().entry(token.hash) {
+            Entry::Occupied(entry) => {
+                let series = entry.get();
 
-        mutable_data.version += 1;
+                Self::check(&series.metadata, instrument, &token)?;
 
-        value
+                Ok(series.slot.clone())
+            }
+            Entry::Vacant(entry) => {
+                let slot = slot();
+
+                entry.insert(Series {
+                    metadata: Metadata::from((instrument, token)),
+                    slot: slot.clone(),
+                });
+
+                self.storage.version.fetch_add(1, Ordering::AcqRel);
+
+                Ok(slot)
+            }
+        }
     }
 }
 
 impl Registry for ProtoBufRegistry {
-    fn counter(&self, token: Token<'_>) -> Counter {
-        Counter::Record(Box::new(Write::new(self.get(Instrument::COUNTER, token))))
+    fn try_counter(&self, token: Token<'_>) -> std::result::Result<Counter, RegistryError> {
+        match self.get(Instrument::COUNTER, token, || {
+            Slot::Value(Default::default())
+        })? {
+            Slot::Value(value) => Ok(Counter::Atomic(value)),
+            Slot::Histogram(_) => unreachable!("checked instrument kind"),
+        }
     }
 
-    fn gauge(&self, token: Token<'_>) -> Gauge {
-        Gauge::Record(Box::new(Write::new(self.get(Instrument::GAUGE, token))))
+    fn try_gauge(&self, token: Token<'_>) -> std::result::Result<Gauge, RegistryError> {
+        match self.get(Instrument::GAUGE, token, || Slot::Value(Default::default()))? {
+            Slot::Value(value) => Ok(Gauge::Atomic(value)),
+            Slot::Histogram(_) => unreachable!("checked instrument kind"),
+        }
     }
 
-    fn histogam(&self, token: Token<'_>) -> Histogram {
-        Histogram::Record(Box::new(Write::new(self.get(Instrument::HISTOGRAM, token))))
+    fn try_histogram(&self, token: Token<'_>) -> std::result::Result<Histogram, RegistryError> {
+        match self.get(Instrument::HISTOGRAM, token, || {
+            Slot::Histogram(Arc::new(AtomicHistogram::new(self.bounds.clone())))
+        })? {
+            Slot::Histogram(histogram) => Ok(Histogram::Atomic(histogram)),
+            Slot::Value(_) => unreachable!("checked instrument kind"),
+        }
     }
 }
@@ -0,0 +1,143 @@
+use metricrs::{Counter, Error, Gauge, Histogram, Registry, Token};
+use metricrs_protobuf::{fetch::Fetch, protos::memory::Query, registry::ProtoBufRegistry};
+
+#[test]
+fn histogram_buckets() {
+    let registry = ProtoBufRegistry::bind_with_buckets("127.0.0.1:0", &[1.0, 2.0, 4.0]).unwrap();
+
+    let histogram = registry.histogam(Token::new("test.histogram", &[]));
+
+    for value in [0.5, 1.0, 1.5, 3.0, 8.0] {
+        histogram.record(value);
+    }
+
+    let mut fetch = Fetch::connect(registry.local_addr()).unwrap();
+
+    let query_result = fetch.query(Query::default()).unwrap();
+
+    assert_eq!(query_result.histograms.len(), 1);
+
+    let value = &query_result.histograms[0];
+
+    assert_eq!(value.bounds, [1.0, 2.0, 4.0]);
+    assert_eq!(value.buckets, [2, 1, 1, 1]);
+    assert_eq!(value.count, 5);
+    assert_eq!(value.sum, 14.0);
+    assert_eq!(value.min, 0.5);
+    assert_eq!(value.max, 8.0);
+}
+
+#[test]
+fn invalid_buckets() {
+    ProtoBufRegistry::bind_with_buckets("127.0.0.1:0", &[2.0, 1.0]).unwrap_err();
+    ProtoBufRegistry::bind_with_buckets("127.0.0.1:0", &[1.0, f64::INFINITY]).unwrap_err();
+}
+
+#[test]
+fn kind_conflict() {
+    let registry = ProtoBufRegistry::bind("127.0.0.1:0").unwrap();
+
+    let counter = registry.counter(Token::new("test.conflict", &[]));
+    let gauge = registry.gauge(Token::new("test.conflict", &[]));
+    let histogram = registry.histogam(Token::new("test.conflict", &[]));
+
+    assert!(matches!(counter, Counter::Atomic(_)));
+    assert!(matches!(gauge, Gauge::Noop));
+    assert!(matches!(histogram, Histogram::Noop));
+
+    assert!(matches!(
+        registry.try_gauge(Token::new("test.conflict", &[])),
+        Err(Error::KindConflict(_))
+    ));
+    assert!(
+        registry
+            .try_counter(Token::new("test.conflict", &[]))
+            .is_ok()
+    );
+
+    gauge.set(1.5);
+    counter.increment(2);
+
+    let mut fetch = Fetch::connect(registry.local_addr()).unwrap();
+
+    let query_result = fetch.query(Query::default()).unwrap();
+
+    assert_eq!(query_result.values.len(), 1);
+    assert_eq!(query_result.values[0].value, 2);
+    assert!(query_result.histograms.is_empty());
+}
+
+#[test]
+fn concurrent_register() {
+    let registry = ProtoBufRegistry::bind("127.0.0.1:0").unwrap();
+
+    let threads = (0..8)
+        .map(|_| {
+            let registry = registry.clone();
+            std::thread::spawn(move || {
+                let counter = registry.counter(Token::new("test.concurrent", &[]));
+
+                for _ in 0..1000 {
+                    counter.increment(1);
+                }
+
+                counter
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let counters = threads
+        .into_iter()
+        .map(|thread| thread.join().unwrap())
+        .collect::<Vec<_>>();
+
+    let mut fetch = Fetch::connect(registry.local_addr()).unwrap();
+
+    let query_result = fetch.query(Query::default()).unwrap();
+
+    assert_eq!(query_result.metadatas.len(), 1);
+    assert_eq!(query_result.values.len(), 1);
+    assert_eq!(query_result.values[0].value, 8000);
+
+    drop(counters);
+
+    // the last value of dropped instruments is reported once more.
+    let query_result = fetch.query(Query::default()).unwrap();
+
+    assert_eq!(query_result.values.len(), 1);
+
+    let query_result = fetch.query(Query::default()).unwrap();
+
+    assert!(query_result.values.is_empty());
+    assert!(query_result.metadatas.is_empty());
+}
+
+#[test]
+fn clone_handle() {
+    let registry = ProtoBufRegistry::bind("127.0.0.1:0").unwrap();
+
+    let counter = registry.counter(Token::new("test.clone", &[]));
+
+    let worker = counter.clone();
+
+    drop(counter);
+
+    let worker = std::thread::spawn(move || {
+        worker.increment(3);
+        worker
+    })
+    .join()
+    .unwrap();
+
+    let mut fetch = Fetch::connect(registry.local_addr()).unwrap();
+
+    // a living clone keeps the instrument registered.
+    for _ in 0..2 {
+        let query_result = fetch.query(Query::default()).unwrap();
+
+        assert_eq!(query_result.values.len(), 1);
+        assert_eq!(query_result.values[0].value, 3);
+    }
+
+    drop(worker);
+}